@@ -1,6 +1,7 @@
 // action.rs
 
 use board::Board;
+use puzzle::Clue;
 
 pub enum PicrossAction {
     NoOp,
@@ -8,10 +9,21 @@ pub enum PicrossAction {
 
     // New(filename)
     New(String),
+    Random,
+    Daily,
 
     Undo,
     Redo,
     Update(Board),
 
     AutoFill,
+    ToggleAutoCross,
+    ToggleClueDone(Clue),
+
+    // Editor
+    ToggleEditor,
+    // ResizeEditor(delta width, delta height)
+    ResizeEditor(i32, i32),
+    CheckUnique,
+    Save,
 }
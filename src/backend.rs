@@ -0,0 +1,74 @@
+// backend.rs
+//
+// Everything the GUI needs from the platform: drawing and input.
+
+use sdl2::event::Event;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+// Sprite sheet, 4 bytes per pixel in R, G, B, A order.
+#[derive(Clone)]
+pub struct Atlas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>
+}
+
+impl Atlas {
+    // A copy with each (from, to) colour replaced, keeping the alpha.
+    pub fn recoloured(&self, colours: &[(Color, Color)]) -> Atlas {
+        let mut pixels = self.pixels.clone();
+
+        for p in pixels.chunks_mut(4) {
+            for &(from, to) in colours {
+                let (fr, fg, fb, _) = from.rgba();
+                if p[0] == fr && p[1] == fg && p[2] == fb {
+                    let (tr, tg, tb, _) = to.rgba();
+                    p[0] = tr;
+                    p[1] = tg;
+                    p[2] = tb;
+                    break;
+                }
+            }
+        }
+
+        Atlas { width: self.width, height: self.height, pixels: pixels }
+    }
+}
+
+// Backends must draw alike, as the tests only see the software backend:
+// fills and outlines with alpha below 255 are blended over what is
+// already on the screen, while clear replaces it.
+pub trait Backend {
+    // (width, height) of the screen in pixels.
+    fn size(&self) -> (u32, u32);
+
+    // Draw the src part of the atlas, scaled to fill dst.
+    fn draw_sprite(&mut self, src: Rect, dst: Rect);
+
+    // As draw_sprite, but filtered rather than nearest neighbour.
+    fn draw_sprite_smooth(&mut self, src: Rect, dst: Rect) {
+        self.draw_sprite(src, dst);
+    }
+
+    fn clear(&mut self, colour: Color);
+    fn fill_rect(&mut self, colour: Color, rect: Rect);
+
+    // Outline of a rectangle, one pixel wide.
+    fn draw_rect(&mut self, colour: Color, rect: Rect);
+
+    fn present(&mut self);
+
+    // Wait up to timeout milliseconds for the next event.
+    fn poll_event(&mut self, timeout: u32) -> Option<Event>;
+
+    // Milliseconds since the backend started.
+    fn ticks(&mut self) -> u32;
+
+    // Redraw the sprites with each (atlas colour, new colour) swapped.
+    fn recolour_sprites(&mut self, colours: &[(Color, Color)]);
+
+    fn toggle_fullscreen(&mut self);
+    fn is_fullscreen(&self) -> bool;
+    fn set_title(&mut self, title: &str);
+}
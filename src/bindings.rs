@@ -0,0 +1,324 @@
+// bindings.rs
+//
+// Which keys and mouse buttons do what.  The defaults can be changed in
+// $XDG_CONFIG_HOME/play_picross/bindings.txt, with one binding per line:
+//
+//   # command key
+//   undo Backspace
+//   redo mouse x2
+//   none A
+//
+// Key names are SDL key names, which may contain spaces, so they come
+// last.  The command 'none' removes a default binding.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead,BufReader};
+use std::path::PathBuf;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+use paths;
+use paths::XdgDir;
+
+const BINDINGS_FILENAME: &'static str = "bindings.txt";
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Command {
+    Undo,
+    Redo,
+    AutoFill,
+    ToggleAutoCross,
+    Random,
+    Daily,
+
+    ToggleEditor,
+    EditorNarrower,
+    EditorWider,
+    EditorShorter,
+    EditorTaller,
+    CheckUnique,
+    Save,
+
+    PaintEmpty,
+    PaintCrossedOut,
+    PaintFilled,
+    NextTool,
+    ZoomToFit,
+    ToggleMinimap,
+    ToggleFullscreen,
+    NextTheme,
+    ToggleClueMarkers,
+
+    // Keyboard cursor
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    Fill,
+    CrossOut,
+    Clear
+}
+
+#[derive(Clone,Copy,Eq,Hash,PartialEq)]
+pub enum Input {
+    Key(Keycode),
+    Mouse(MouseButton)
+}
+
+pub struct Bindings {
+    map: HashMap<Input, Command>
+}
+
+// Names in the bindings file.
+const COMMAND_NAMES: &'static [(&'static str, Command)] = &[
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+    ("auto_fill", Command::AutoFill),
+    ("toggle_auto_cross", Command::ToggleAutoCross),
+    ("random", Command::Random),
+    ("daily", Command::Daily),
+    ("toggle_editor", Command::ToggleEditor),
+    ("editor_narrower", Command::EditorNarrower),
+    ("editor_wider", Command::EditorWider),
+    ("editor_shorter", Command::EditorShorter),
+    ("editor_taller", Command::EditorTaller),
+    ("check_unique", Command::CheckUnique),
+    ("save", Command::Save),
+    ("paint_empty", Command::PaintEmpty),
+    ("paint_crossed_out", Command::PaintCrossedOut),
+    ("paint_filled", Command::PaintFilled),
+    ("next_tool", Command::NextTool),
+    ("zoom_to_fit", Command::ZoomToFit),
+    ("toggle_minimap", Command::ToggleMinimap),
+    ("toggle_fullscreen", Command::ToggleFullscreen),
+    ("next_theme", Command::NextTheme),
+    ("toggle_clue_markers", Command::ToggleClueMarkers),
+    ("cursor_left", Command::CursorLeft),
+    ("cursor_right", Command::CursorRight),
+    ("cursor_up", Command::CursorUp),
+    ("cursor_down", Command::CursorDown),
+    ("fill", Command::Fill),
+    ("cross_out", Command::CrossOut),
+    ("clear", Command::Clear)
+];
+
+const DEFAULT_KEYS: &'static [(Keycode, Command)] = &[
+    (Keycode::Z, Command::Undo),
+    (Keycode::X, Command::Redo),
+    (Keycode::A, Command::AutoFill),
+    (Keycode::O, Command::ToggleAutoCross),
+    (Keycode::N, Command::Random),
+    (Keycode::D, Command::Daily),
+
+    (Keycode::E, Command::ToggleEditor),
+    (Keycode::LeftBracket, Command::EditorNarrower),
+    (Keycode::RightBracket, Command::EditorWider),
+    (Keycode::Minus, Command::EditorShorter),
+    (Keycode::Equals, Command::EditorTaller),
+    (Keycode::U, Command::CheckUnique),
+    (Keycode::S, Command::Save),
+
+    (Keycode::Num1, Command::PaintEmpty),
+    (Keycode::Num2, Command::PaintCrossedOut),
+    (Keycode::Num3, Command::PaintFilled),
+    (Keycode::T, Command::NextTool),
+    (Keycode::Num0, Command::ZoomToFit),
+    (Keycode::M, Command::ToggleMinimap),
+    (Keycode::F, Command::ToggleFullscreen),
+    (Keycode::F11, Command::ToggleFullscreen),
+    (Keycode::V, Command::NextTheme),
+    (Keycode::B, Command::ToggleClueMarkers),
+
+    (Keycode::Left, Command::CursorLeft),
+    (Keycode::H, Command::CursorLeft),
+    (Keycode::Right, Command::CursorRight),
+    (Keycode::L, Command::CursorRight),
+    (Keycode::Up, Command::CursorUp),
+    (Keycode::K, Command::CursorUp),
+    (Keycode::Down, Command::CursorDown),
+    (Keycode::J, Command::CursorDown),
+    (Keycode::Space, Command::Fill),
+    (Keycode::C, Command::CrossOut),
+    (Keycode::Backspace, Command::Clear),
+    (Keycode::Delete, Command::Clear)
+];
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        let mut map = HashMap::new();
+
+        for &(k, cmd) in DEFAULT_KEYS {
+            map.insert(Input::Key(k), cmd);
+        }
+        map.insert(Input::Mouse(MouseButton::X1), Command::Undo);
+        map.insert(Input::Mouse(MouseButton::X2), Command::Redo);
+
+        Bindings { map: map }
+    }
+
+    // The defaults, changed by the user's bindings file if there is one.
+    // Lines with problems are reported and skipped.
+    pub fn load_user() -> Bindings {
+        let mut bindings = Bindings::new();
+        let path = match bindings_path() {
+            Some(p) => p,
+            None => return bindings
+        };
+
+        let file = match File::open(&path) {
+            Ok(f) => f,
+            Err(_) => return bindings
+        };
+
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = match line {
+                Ok(ln) => ln,
+                Err(e) => {
+                    println!("{}: {}", path.display(), e);
+                    break;
+                }
+            };
+
+            if let Err(msg) = bindings.read_line(&line) {
+                println!("{} line {}: {}", path.display(), i + 1, msg);
+            }
+        }
+
+        bindings
+    }
+
+    pub fn get(&self, input: Input) -> Option<Command> {
+        self.map.get(&input).map(|&cmd| cmd)
+    }
+
+    fn read_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("#") {
+            return Ok(())
+        }
+
+        let (name, input_name) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => return Err(format!("no key given for '{}'", line))
+        };
+
+        let input = parse_input(input_name)?;
+
+        if name == "none" {
+            self.map.remove(&input);
+            return Ok(())
+        }
+
+        match COMMAND_NAMES.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, cmd)) => {
+                self.map.insert(input, cmd);
+                Ok(())
+            },
+            None => Err(format!("unknown command '{}'", name))
+        }
+    }
+}
+
+// A key name, or 'mouse x1' or 'mouse x2'.  The other mouse buttons
+// paint and move the board.
+fn parse_input(s: &str) -> Result<Input, String> {
+    let ws: Vec<&str> = s.split_whitespace().collect();
+
+    if ws.len() == 2 && ws[0].eq_ignore_ascii_case("mouse") {
+        return match ws[1].to_lowercase().as_str() {
+            "x1" => Ok(Input::Mouse(MouseButton::X1)),
+            "x2" => Ok(Input::Mouse(MouseButton::X2)),
+            _ => Err(format!("cannot bind mouse button '{}'", ws[1]))
+        }
+    }
+
+    match Keycode::from_name(s) {
+        Some(k) => Ok(Input::Key(k)),
+        None => Err(format!("unknown key '{}'", s))
+    }
+}
+
+// $XDG_CONFIG_HOME/play_picross/bindings.txt
+fn bindings_path() -> Option<PathBuf> {
+    paths::xdg_file(XdgDir::Config, BINDINGS_FILENAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(k: Keycode) -> Input {
+        Input::Key(k)
+    }
+
+    #[test]
+    fn parse_inputs() {
+        assert!(parse_input("Backspace") == Ok(key(Keycode::Backspace)));
+        assert!(parse_input("Left Shift") == Ok(key(Keycode::LShift)));
+        assert!(parse_input("mouse x2") == Ok(Input::Mouse(MouseButton::X2)));
+        assert!(parse_input("Mouse X1") == Ok(Input::Mouse(MouseButton::X1)));
+
+        assert!(parse_input("mouse left").is_err());
+        assert!(parse_input("NoSuchKey").is_err());
+    }
+
+    #[test]
+    fn read_valid_lines() {
+        let mut b = Bindings::new();
+
+        assert!(b.read_line("# command key").is_ok());
+        assert!(b.read_line("   ").is_ok());
+        assert!(b.read_line("undo Backspace").is_ok());
+        assert!(b.read_line("  redo   mouse x1  ").is_ok());
+        assert!(b.read_line("toggle_minimap Left Shift").is_ok());
+
+        assert!(b.get(key(Keycode::Backspace)) == Some(Command::Undo));
+        assert!(b.get(Input::Mouse(MouseButton::X1)) == Some(Command::Redo));
+        assert!(b.get(key(Keycode::LShift)) == Some(Command::ToggleMinimap));
+
+        // the defaults are still there
+        assert!(b.get(key(Keycode::Z)) == Some(Command::Undo));
+    }
+
+    #[test]
+    fn bad_lines_change_nothing() {
+        let mut b = Bindings::new();
+
+        assert!(b.read_line("undo").is_err());
+        assert!(b.read_line("undo NoSuchKey").is_err());
+        assert!(b.read_line("no_such_command A").is_err());
+        assert!(b.read_line("none NoSuchKey").is_err());
+
+        assert!(b.get(key(Keycode::A)) == Some(Command::AutoFill));
+    }
+
+    #[test]
+    fn duplicate_bindings() {
+        let mut b = Bindings::new();
+
+        // the last binding of a key wins
+        assert!(b.read_line("undo Q").is_ok());
+        assert!(b.read_line("redo Q").is_ok());
+        assert!(b.get(key(Keycode::Q)) == Some(Command::Redo));
+
+        // a command may have many keys
+        assert!(b.read_line("undo W").is_ok());
+        assert!(b.get(key(Keycode::W)) == Some(Command::Undo));
+        assert!(b.get(key(Keycode::Z)) == Some(Command::Undo));
+    }
+
+    #[test]
+    fn none_unbinds() {
+        let mut b = Bindings::new();
+
+        assert!(b.read_line("none A").is_ok());
+        assert!(b.read_line("none mouse x2").is_ok());
+        assert!(b.get(key(Keycode::A)).is_none());
+        assert!(b.get(Input::Mouse(MouseButton::X2)).is_none());
+
+        // and the key can be bound again
+        assert!(b.read_line("auto_fill A").is_ok());
+        assert!(b.get(key(Keycode::A)) == Some(Command::AutoFill));
+    }
+}
@@ -1,7 +1,11 @@
 // board.rs
 
+use std::cmp::min;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
 
+use puzzle::Clue;
 use puzzle::Rule;
 use puzzle::Rules;
 
@@ -12,6 +16,18 @@ pub enum Tile {
     CrossedOut
 }
 
+// Outcome of one round of line-by-line deduction.
+pub enum Deduction {
+    // Some tiles could be deduced.
+    Progress(Board),
+
+    // No more tiles can be deduced from individual lines.
+    Stuck,
+
+    // Some line can not be completed given its rule.
+    Conflict
+}
+
 #[derive(Clone,Copy,Eq,PartialEq)]
 enum AutoFillTile {
     // State of board is filled or crossed out.
@@ -39,11 +55,17 @@ enum AutoFillResult {
     Conflict,
 }
 
-#[derive(Clone)]
+type WorkXYT = (u32, u32, Tile);
+
+#[derive(Clone,Eq,PartialEq)]
 pub struct Board {
     pub width: usize,
     pub height: usize,
-    tiles: Vec<Tile>
+    tiles: Vec<Tile>,
+
+    // clues the player has marked as done, kept with the tiles so that
+    // marking them can be undone
+    done: HashSet<Clue>
 }
 
 impl Board {
@@ -54,7 +76,18 @@ impl Board {
         Board {
             width: width,
             height: height,
-            tiles: ts
+            tiles: ts,
+            done: HashSet::new()
+        }
+    }
+
+    pub fn is_done(&self, clue: Clue) -> bool {
+        self.done.contains(&clue)
+    }
+
+    pub fn toggle_done(&mut self, clue: Clue) {
+        if !self.done.remove(&clue) {
+            self.done.insert(clue);
         }
     }
 
@@ -80,6 +113,61 @@ impl Board {
         }
     }
 
+    // Copy of the board with new dimensions, keeping the overlapping tiles.
+    pub fn resize(&self, width: usize, height: usize) -> Board {
+        let mut b = Board::new(width, height);
+
+        for y in 0..min(height, self.height) {
+            for x in 0..min(width, self.width) {
+                b.tiles[width * y + x] = self.at(x, y);
+            }
+        }
+
+        b
+    }
+
+    // True if nothing has been filled, crossed out or marked as done.
+    pub fn is_blank(&self) -> bool {
+        self.tiles.iter().all(|&t| t == Tile::Empty) && self.done.is_empty()
+    }
+
+    pub fn find_empty(&self) -> Option<(u32, u32)> {
+        self.tiles.iter().position(|&t| t == Tile::Empty)
+            .map(|i| ((i % self.width) as u32, (i / self.width) as u32))
+    }
+
+    pub fn find_difference(&self, other: &Board) -> Option<(u32, u32)> {
+        assert!(self.width == other.width && self.height == other.height);
+        self.tiles.iter().zip(other.tiles.iter()).position(|(a, b)| a != b)
+            .map(|i| ((i % self.width) as u32, (i / self.width) as u32))
+    }
+
+    pub fn get_column(&self, x: usize) -> Vec<Tile> {
+        (0..self.height).map(|y| self.at(x, y)).collect()
+    }
+
+    pub fn get_row(&self, y: usize) -> Vec<Tile> {
+        self.tiles[self.width * y .. self.width * (y + 1)].to_vec()
+    }
+
+    // Lengths of the runs of filled tiles in a column.
+    pub fn get_column_segments(&self, x: usize) -> Rule {
+        let slice: Vec<Tile> = (0..self.height).map(|y| self.at(x, y)).collect();
+        find_segments(&slice)
+    }
+
+    // Lengths of the runs of filled tiles in a row.
+    pub fn get_row_segments(&self, y: usize) -> Rule {
+        find_segments(&self.tiles[self.width * y .. self.width * (y + 1)])
+    }
+
+    pub fn is_solved(&self, rules: Rules) -> bool {
+        let (col_rules, row_rules) = rules;
+
+        col_rules.iter().enumerate().all(|(x, r)| self.get_column_segments(x) == *r)
+        && row_rules.iter().enumerate().all(|(y, r)| self.get_row_segments(y) == *r)
+    }
+
     pub fn get_completed_column_segments_from_head(&self, x: usize) -> Vec<u32> {
         let mut segments = Vec::new();
 
@@ -208,17 +296,68 @@ impl Board {
         segments
     }
 
+    // Cross out the empty tiles of rows and columns whose filled tiles
+    // already match their rule.
+    pub fn auto_cross(&self, rules: Rules) -> Board {
+        let (col_rules, row_rules) = rules;
+        let mut b = self.clone();
+
+        for (y, r) in row_rules.iter().enumerate() {
+            if self.get_row_segments(y) == *r {
+                for x in 0..self.width {
+                    if b.at(x, y) == Tile::Empty {
+                        b.tiles[self.width * y + x] = Tile::CrossedOut;
+                    }
+                }
+            }
+        }
+
+        for (x, r) in col_rules.iter().enumerate() {
+            if self.get_column_segments(x) == *r {
+                for y in 0..self.height {
+                    if b.at(x, y) == Tile::Empty {
+                        b.tiles[self.width * y + x] = Tile::CrossedOut;
+                    }
+                }
+            }
+        }
+
+        b
+    }
+
     pub fn autofill(&self, rules: Rules) -> Option<Board> {
-        type WorkXYT = (u32, u32, Tile);
+        let (work, _) = self.find_autofill_work(rules);
+        self.apply_work(&work)
+    }
+
+    // Like autofill, but also reports lines that can not be completed.
+    pub fn deduce(&self, rules: Rules) -> Deduction {
+        let (work, conflict) = self.find_autofill_work(rules);
+
+        if conflict {
+            Deduction::Conflict
+        } else if let Some(b) = self.apply_work(&work) {
+            Deduction::Progress(b)
+        } else {
+            Deduction::Stuck
+        }
+    }
+
+    // Returns the tiles to change, and whether any line had no solution.
+    fn find_autofill_work(&self, rules: Rules) -> (Vec<WorkXYT>, bool) {
         let (col_rules, row_rules) = rules;
         let mut work: Vec<WorkXYT> = Vec::new();
+        let mut conflict = false;
 
         for (row, rule) in row_rules.iter().enumerate() {
             let mut trial = self.make_row_slice(row);
             let mut accum = trial.clone();
             let mut cache = HashMap::new();
 
-            try_autofill(&mut trial, 0, rule, 0, &mut accum, &mut cache);
+            let r = try_autofill(&mut trial, 0, rule, 0, &mut accum, &mut cache);
+            if r == AutoFillResult::Conflict {
+                conflict = true;
+            }
 
             for (x, &t) in accum.iter().enumerate() {
                 if t == AutoFillTile::CanBeFilled {
@@ -234,7 +373,10 @@ impl Board {
             let mut accum = trial.clone();
             let mut cache = HashMap::new();
 
-            try_autofill(&mut trial, 0, rule, 0, &mut accum, &mut cache);
+            let r = try_autofill(&mut trial, 0, rule, 0, &mut accum, &mut cache);
+            if r == AutoFillResult::Conflict {
+                conflict = true;
+            }
 
             for (y, &t) in accum.iter().enumerate() {
                 if t == AutoFillTile::CanBeFilled {
@@ -245,6 +387,10 @@ impl Board {
             }
         }
 
+        (work, conflict)
+    }
+
+    fn apply_work(&self, work: &[WorkXYT]) -> Option<Board> {
         if !work.is_empty() {
             let mut b = self.clone();
             for &(x, y, t) in work.iter() {
@@ -268,6 +414,44 @@ impl Board {
     }
 }
 
+// One line of text per row: '#' filled, '.' crossed out, '?' empty.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            let row: String = (0..self.width).map(|x|
+                    match self.at(x, y) {
+                        Tile::Empty => '?',
+                        Tile::Filled => '#',
+                        Tile::CrossedOut => '.'
+                    }).collect();
+
+            writeln!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn find_segments(slice: &[Tile]) -> Rule {
+    let mut segments = Vec::new();
+    let mut count = 0;
+
+    for &t in slice.iter() {
+        if t == Tile::Filled {
+            count += 1;
+        } else if count > 0 {
+            segments.push(count);
+            count = 0;
+        }
+    }
+
+    if count > 0 {
+        segments.push(count);
+    }
+
+    segments
+}
+
 fn make_autofill_tile(t: Tile) -> AutoFillTile {
     match t {
         Tile::Empty => AutoFillTile::NoSolutionFound,
@@ -0,0 +1,235 @@
+// cli.rs
+//
+// Subcommands that work on puzzle files without opening a window.
+
+#[cfg(feature = "gui")]
+use std::path::Path;
+
+#[cfg(feature = "gui")]
+use play_picross::gfx;
+use play_picross::generator::Difficulty;
+use play_picross::puzzle::Puzzle;
+#[cfg(feature = "gui")]
+use play_picross::replay;
+use play_picross::solver;
+use play_picross::solver::Solution;
+
+// Exit codes.
+const EXIT_OK: i32 = 0;
+// The puzzle is not uniquely solvable.
+const EXIT_BAD_PUZZLE: i32 = 1;
+// The command line or a puzzle file could not be read.
+const EXIT_ERROR: i32 = 2;
+
+pub const USAGE: &'static str =
+"       play_picross solve FILE
+       play_picross validate FILE...
+       play_picross rate FILE...
+       play_picross lint FILE...
+       play_picross convert IN OUT
+       play_picross replay EVENTS PUZZLE [SNAPSHOT_DIR]";
+
+// Run a subcommand, returning the exit code.
+// Returns None if the arguments do not name a subcommand.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (cmd, files) = match args.split_first() {
+        Some((cmd, files)) => (cmd.as_str(), files),
+        None => return None
+    };
+
+    let ok_arity = match cmd {
+        "solve" => files.len() == 1,
+        "validate" | "rate" | "lint" => !files.is_empty(),
+        "convert" => files.len() == 2,
+        "replay" => files.len() == 2 || files.len() == 3,
+        _ => return None
+    };
+
+    if !ok_arity {
+        eprintln!("Usage:\n{}", USAGE);
+        return Some(EXIT_ERROR)
+    }
+
+    let code = match cmd {
+        "solve" => solve(&files[0]),
+        "convert" => convert(&files[0], &files[1]),
+        "replay" => replay(&files[0], &files[1], files.get(2)),
+        "validate" => files.iter().map(validate).max().unwrap(),
+        "lint" => files.iter().map(lint).max().unwrap(),
+        _ => files.iter().map(rate).max().unwrap()
+    };
+
+    Some(code)
+}
+
+fn load(filename: &String) -> Option<Puzzle> {
+    match Puzzle::load_file(filename) {
+        Ok(p) => Some(p),
+        Err(e) => {
+            eprintln!("{}: {}", filename, e);
+            None
+        }
+    }
+}
+
+// Print the solution as a grid.
+fn solve(filename: &String) -> i32 {
+    let p = match load(filename) {
+        Some(p) => p,
+        None => return EXIT_ERROR
+    };
+
+    let b = p.get_board();
+    match solver::solve(b.width, b.height, p.get_rules()) {
+        Solution::Unique(s) => {
+            print!("{}", s);
+            EXIT_OK
+        },
+
+        Solution::Multiple(first, _) => {
+            println!("{}: multiple solutions, one of which is:", filename);
+            print!("{}", first);
+            EXIT_BAD_PUZZLE
+        },
+
+        Solution::NoSolution => {
+            println!("{}: no solution", filename);
+            EXIT_BAD_PUZZLE
+        }
+    }
+}
+
+fn validate(filename: &String) -> i32 {
+    let p = match load(filename) {
+        Some(p) => p,
+        None => return EXIT_ERROR
+    };
+
+    let b = p.get_board();
+    match solver::solve(b.width, b.height, p.get_rules()) {
+        Solution::Unique(_) => {
+            println!("{}: ok", filename);
+            EXIT_OK
+        },
+
+        Solution::Multiple(..) => {
+            println!("{}: multiple solutions", filename);
+            EXIT_BAD_PUZZLE
+        },
+
+        Solution::NoSolution => {
+            println!("{}: no solution", filename);
+            EXIT_BAD_PUZZLE
+        }
+    }
+}
+
+// Rate by how much line logic is needed to solve the puzzle.
+fn rate(filename: &String) -> i32 {
+    let p = match load(filename) {
+        Some(p) => p,
+        None => return EXIT_ERROR
+    };
+
+    let (width, height) = (p.get_board().width, p.get_board().height);
+    if let Some((b, passes)) = solver::line_solve(width, height, p.get_rules()) {
+        if b.find_empty().is_none() {
+            let d = Difficulty::from_passes(passes, width, height);
+            println!("{}: {} ({} rounds of line logic)", filename, d.name(), passes);
+            return EXIT_OK
+        }
+    }
+
+    match solver::solve(width, height, p.get_rules()) {
+        Solution::Unique(_) => {
+            println!("{}: too hard for line logic, needs guessing", filename);
+            EXIT_OK
+        },
+
+        Solution::Multiple(..) => {
+            println!("{}: multiple solutions", filename);
+            EXIT_BAD_PUZZLE
+        },
+
+        Solution::NoSolution => {
+            println!("{}: no solution", filename);
+            EXIT_BAD_PUZZLE
+        }
+    }
+}
+
+// Warn about clues that contradict each other, without solving.
+fn lint(filename: &String) -> i32 {
+    let warnings = match Puzzle::lint_file(filename) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("{}: {}", filename, e);
+            return EXIT_ERROR
+        }
+    };
+
+    if warnings.is_empty() {
+        println!("{}: ok", filename);
+        return EXIT_OK
+    }
+
+    for w in warnings.iter() {
+        println!("{}: {}", filename, w);
+    }
+    EXIT_BAD_PUZZLE
+}
+
+fn convert(in_filename: &String, out_filename: &String) -> i32 {
+    let p = match load(in_filename) {
+        Some(p) => p,
+        None => return EXIT_ERROR
+    };
+
+    match p.save_file(out_filename) {
+        Ok(_) => EXIT_OK,
+        Err(e) => {
+            eprintln!("{}: {}", out_filename, e);
+            EXIT_ERROR
+        }
+    }
+}
+
+// Play back recorded events and print the final board.
+#[cfg(feature = "gui")]
+fn replay(events_filename: &String, puzzle_filename: &String,
+        snapshot_dir: Option<&String>) -> i32 {
+    let events = match replay::load_events(events_filename) {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("{}: {}", events_filename, e);
+            return EXIT_ERROR
+        }
+    };
+
+    let p = match load(puzzle_filename) {
+        Some(p) => p,
+        None => return EXIT_ERROR
+    };
+
+    // the atlas is only needed to draw the snapshots
+    let atlas = snapshot_dir.and_then(|_| gfx::load_atlas());
+    let dir = snapshot_dir.map(Path::new);
+
+    match replay::replay(p, events, atlas, dir) {
+        Ok(b) => {
+            print!("{}", b);
+            EXIT_OK
+        },
+
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_ERROR
+        }
+    }
+}
+
+#[cfg(not(feature = "gui"))]
+fn replay(_: &String, _: &String, _: Option<&String>) -> i32 {
+    eprintln!("Built without the gui feature, cannot replay events");
+    EXIT_ERROR
+}
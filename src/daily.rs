@@ -0,0 +1,209 @@
+// daily.rs
+
+use std::fmt;
+use std::fs;
+use std::fs::{File,OpenOptions};
+use std::io;
+use std::io::{BufRead,BufReader,Write};
+use std::path::PathBuf;
+use std::time::{SystemTime,UNIX_EPOCH};
+
+use generator;
+use generator::{Difficulty,GeneratorOptions};
+use paths;
+use paths::XdgDir;
+use puzzle::Puzzle;
+
+const DAILY_WIDTH: usize = 15;
+const DAILY_HEIGHT: usize = 15;
+const DAILY_DENSITY: f64 = 0.6;
+const TIMES_FILENAME: &str = "daily.txt";
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32
+}
+
+impl Date {
+    // Today's date in UTC, so that everyone gets the same puzzle
+    // regardless of time zone.
+    pub fn today() -> Date {
+        let secs = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(_) => 0
+        };
+
+        Date::from_days(secs.div_euclid(86400))
+    }
+
+    // Parse "YYYY-MM-DD".
+    pub fn parse(s: &str) -> Option<Date> {
+        let ws: Vec<&str> = s.trim().split('-').collect();
+        if ws.len() != 3 {
+            return None
+        }
+
+        let year = ws[0].parse::<i32>().ok()?;
+        let month = ws[1].parse::<u32>().ok()?;
+        let day = ws[2].parse::<u32>().ok()?;
+        let d = Date { year, month, day };
+
+        // reject dates such as 2023-02-30
+        if (1..=12).contains(&month) && day >= 1
+            && Date::from_days(d.to_days()) == d {
+            Some(d)
+        } else {
+            None
+        }
+    }
+
+    // Days since 1970-01-01, from Howard Hinnant's civil calendar algorithms.
+    fn to_days(self) -> i64 {
+        let y = (if self.month <= 2 { self.year - 1 } else { self.year }) as i64;
+        let m = self.month as i64;
+        let d = self.day as i64;
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+        era * 146097 + doe - 719468
+    }
+
+    fn from_days(days: i64) -> Date {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let d = doy - (153 * mp + 2) / 5 + 1;
+        let m = if mp < 10 { mp + 3 } else { mp - 9 };
+        let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+
+        Date { year: y as i32, month: m as u32, day: d as u32 }
+    }
+
+    fn seed(&self) -> u64 {
+        // splitmix64 finaliser, so that consecutive days look unrelated
+        let mut z = (self.to_days() as u64).wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// The puzzle for the given date, derived from nothing but the date.
+pub fn daily_puzzle(date: Date) -> Option<Puzzle> {
+    let mut opts = GeneratorOptions::new(DAILY_WIDTH, DAILY_HEIGHT);
+    opts.density = DAILY_DENSITY;
+    opts.difficulty = Some(Difficulty::Medium);
+
+    generator::generate(&opts, date.seed()).or_else(|| {
+        opts.difficulty = None;
+        generator::generate(&opts, date.seed())
+    })
+}
+
+// Best completion time previously recorded for the date, in seconds.
+pub fn best_time(date: Date) -> Option<u64> {
+    let path = times_path()?;
+    let file = File::open(path).ok()?;
+    let key = date.to_string();
+    let mut best: Option<u64> = None;
+
+    for line in BufReader::new(file).lines() {
+        let ln = match line {
+            Ok(ln) => ln,
+            Err(_) => break
+        };
+
+        let ws: Vec<&str> = ln.split_whitespace().collect();
+        if ws.len() == 2 && ws[0] == key {
+            if let Ok(t) = ws[1].parse::<u64>() {
+                best = Some(best.map_or(t, |b| b.min(t)));
+            }
+        }
+    }
+
+    best
+}
+
+pub fn record_time(date: Date, seconds: u64) -> io::Result<()> {
+    let path = times_path().ok_or(
+            io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{} {}", date, seconds)
+}
+
+// $XDG_DATA_HOME/play_picross/daily.txt
+fn times_path() -> Option<PathBuf> {
+    paths::xdg_file(XdgDir::Data, TIMES_FILENAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn days_round_trip() {
+        // across 1900, 2000 and 2100, which are and are not leap years
+        let mut days = date(1899, 12, 1).to_days();
+        let last = date(2101, 3, 31).to_days();
+        let mut prev = Date::from_days(days - 1);
+
+        while days <= last {
+            let d = Date::from_days(days);
+            assert!(d.to_days() == days);
+            assert!(Date::parse(&d.to_string()) == Some(d));
+            assert!(d.day == prev.day + 1 || (d.day == 1 && d.month != prev.month));
+            prev = d;
+            days = days + 1;
+        }
+
+        assert!(date(1970, 1, 1).to_days() == 0);
+        assert!(date(2000, 3, 1).to_days() - date(2000, 2, 28).to_days() == 2);
+        assert!(date(2100, 3, 1).to_days() - date(2100, 2, 28).to_days() == 1);
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert!(Date::parse("2023-02-30").is_none());
+        assert!(Date::parse("2023-02-29").is_none());
+        assert!(Date::parse("1900-02-29").is_none());
+        assert!(Date::parse("2023-04-31").is_none());
+        assert!(Date::parse("2023-13-01").is_none());
+        assert!(Date::parse("2023-00-10").is_none());
+        assert!(Date::parse("2023-01-00").is_none());
+        assert!(Date::parse("2023-01").is_none());
+
+        assert!(Date::parse("2024-02-29") == Some(date(2024, 2, 29)));
+        assert!(Date::parse("2000-02-29") == Some(date(2000, 2, 29)));
+    }
+
+    #[test]
+    fn daily_puzzle_depends_only_on_date() {
+        let a = daily_puzzle(date(2024, 2, 29)).unwrap();
+        let b = daily_puzzle(date(2024, 2, 29)).unwrap();
+        let c = daily_puzzle(date(2024, 3, 1)).unwrap();
+        assert!(a.get_rules() == b.get_rules());
+        assert!(a.get_rules() != c.get_rules());
+    }
+}
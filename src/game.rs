@@ -0,0 +1,364 @@
+// game.rs
+//
+// Applies the actions coming from the Gui to the puzzle being played.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use action::PicrossAction;
+use board::Board;
+use daily;
+use daily::Date;
+use generator;
+use generator::GeneratorOptions;
+use gui::Gui;
+use paths;
+use paths::XdgDir;
+use puzzle::Puzzle;
+use solver;
+use solver::Solution;
+
+// Where the editor saves puzzles, as untitled.txt, untitled-2.txt and
+// so on in $XDG_DATA_HOME/play_picross, never replacing a file.
+const EDITOR_FILENAME: &'static str = "untitled";
+const EDITOR_EXTENSION: &'static str = "txt";
+
+// Actions that throw away the board, which must be asked for twice when
+// there is something on it.
+#[derive(Clone,Copy,Eq,PartialEq)]
+enum Discard {
+    Editor,
+    Random,
+    Daily
+}
+
+pub struct Game {
+    puzzle: Puzzle,
+    random_opts: GeneratorOptions,
+    editing: bool,
+
+    // cross out the rest of lines that match their rule
+    auto_cross: bool,
+
+    // Some(date, start time, assisted) while playing a daily puzzle,
+    // where assisted is set once auto-fill has been used
+    daily: Option<(Date, Instant, bool)>,
+
+    // Some(action) asked for once, waiting to be asked for again
+    confirm: Option<Discard>,
+
+    // false to not save puzzles or daily times, as when replaying
+    write_files: bool
+}
+
+impl Game {
+    pub fn new(puzzle: Puzzle, random_opts: GeneratorOptions) -> Game {
+        Game {
+            puzzle: puzzle,
+            random_opts: random_opts,
+            editing: false,
+            auto_cross: false,
+            daily: None,
+            confirm: None,
+            write_files: true
+        }
+    }
+
+    // Start timing a daily puzzle, which should be the current puzzle.
+    pub fn set_daily(&mut self, date: Date) {
+        self.daily = Some((date, Instant::now(), false));
+    }
+
+    pub fn set_auto_cross(&mut self, auto_cross: bool) {
+        self.auto_cross = auto_cross;
+    }
+
+    pub fn set_write_files(&mut self, write_files: bool) {
+        self.write_files = write_files;
+    }
+
+    pub fn get_auto_cross(&self) -> bool {
+        self.auto_cross
+    }
+
+    pub fn get_puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    pub fn run(&mut self, gui: &mut Gui) {
+        gui.on_new_puzzle(&self.puzzle);
+
+        while self.step(gui) {
+        }
+    }
+
+    // Handle one input and redraw.  Returns false when it is time to quit.
+    pub fn step(&mut self, gui: &mut Gui) -> bool {
+        let action = gui.read_input(&self.puzzle);
+
+        // actions that may change the board, and so the editor's rules
+        let board_changed = matches!(action,
+                PicrossAction::Undo | PicrossAction::Redo |
+                PicrossAction::Update(_) | PicrossAction::ToggleEditor);
+
+        // any other action cancels a confirmation
+        let confirm = match action {
+            PicrossAction::NoOp => self.confirm,
+            _ => self.confirm.take()
+        };
+
+        match action {
+            PicrossAction::NoOp => {},
+            PicrossAction::Quit => return false,
+
+            PicrossAction::New(filename) =>
+                match Puzzle::load_file(&filename) {
+                    Ok(p) => {
+                        self.puzzle = p;
+                        self.editing = false;
+                        self.daily = None;
+                        gui.on_new_puzzle(&self.puzzle);
+                    },
+
+                    Err(e) => {
+                        println!("{}: {}", filename, e);
+                        gui.on_load_error(&filename, &e);
+                    }
+                },
+
+            PicrossAction::Undo => self.puzzle.undo(),
+            PicrossAction::Redo => self.puzzle.redo(),
+            PicrossAction::Update(new_b) => self.update(new_b),
+
+            PicrossAction::AutoFill =>
+                if !self.editing {
+                    if let Some(new_b) = self.puzzle.get_board().autofill(self.puzzle.get_rules()) {
+                        self.update(new_b);
+                        if let Some((_, _, ref mut assisted)) = self.daily {
+                            *assisted = true;
+                        }
+                    }
+                },
+
+            PicrossAction::ToggleClueDone(clue) =>
+                if !self.editing {
+                    self.puzzle.toggle_done(clue);
+                },
+
+            PicrossAction::ToggleAutoCross => {
+                self.auto_cross = !self.auto_cross;
+                report(gui, &format!("Auto-cross {}", if self.auto_cross { "on" } else { "off" }));
+            },
+
+            PicrossAction::Random =>
+                if self.may_discard(gui, confirm, Discard::Random) {
+                    let b = self.puzzle.get_board();
+                    self.random_opts.width = b.width;
+                    self.random_opts.height = b.height;
+
+                    if let Some(p) = generate_random(&self.random_opts, generator::random_seed()) {
+                        self.puzzle = p;
+                        self.editing = false;
+                        self.daily = None;
+                        gui.on_new_puzzle(&self.puzzle);
+                    }
+                },
+
+            PicrossAction::Daily =>
+                if self.may_discard(gui, confirm, Discard::Daily) {
+                    let date = Date::today();
+
+                    if let Some(p) = start_daily(date) {
+                        self.puzzle = p;
+                        self.editing = false;
+                        self.daily = Some((date, Instant::now(), false));
+                        gui.on_new_puzzle(&self.puzzle);
+                    }
+                },
+
+            PicrossAction::ToggleEditor =>
+                if self.editing || self.may_discard(gui, confirm, Discard::Editor) {
+                    let b = self.puzzle.get_board().clone();
+                    if self.editing {
+                        // play the puzzle that was drawn
+                        self.puzzle = Puzzle::from_solution(&b);
+                    } else {
+                        self.puzzle = Puzzle::new(b.width, b.height);
+                    }
+                    self.editing = !self.editing;
+                    self.daily = None;
+                    gui.on_new_puzzle(&self.puzzle);
+                },
+
+            PicrossAction::ResizeEditor(dw, dh) =>
+                if self.editing {
+                    let b = self.puzzle.get_board();
+                    let w = b.width as i32 + dw;
+                    let h = b.height as i32 + dh;
+                    if w > 0 && h > 0 {
+                        self.puzzle = self.puzzle.resize(w as usize, h as usize);
+                        gui.on_new_puzzle(&self.puzzle);
+                    }
+                },
+
+            PicrossAction::CheckUnique =>
+                if self.editing {
+                    let b = self.puzzle.get_board();
+                    match solver::solve(b.width, b.height, self.puzzle.get_rules()) {
+                        Solution::Unique(_) =>
+                            report(gui, "Puzzle has a unique solution"),
+                        Solution::Multiple(first, second) => {
+                            report(gui, "Puzzle has multiple solutions");
+                            if let Some((x, y)) = first.find_difference(&second) {
+                                report(gui, &format!("Tile ({}, {}) is ambiguous", x + 1, y + 1));
+                            }
+                        },
+                        Solution::NoSolution =>
+                            report(gui, "Puzzle has no solution"),
+                    }
+                },
+
+            PicrossAction::Save =>
+                if self.editing && !self.write_files {
+                    report(gui, "Saving is turned off");
+                } else if self.editing {
+                    match editor_path() {
+                        Some(path) => {
+                            let filename = path.display().to_string();
+                            let saved = match path.parent() {
+                                Some(dir) => fs::create_dir_all(dir),
+                                None => Ok(())
+                            }.and_then(|_| self.puzzle.save_file(&filename));
+
+                            match saved {
+                                Ok(_) => report(gui, &format!("Saved puzzle to {}", filename)),
+                                Err(e) => report(gui, &format!("{}: {}", filename, e))
+                            }
+                        },
+                        None => report(gui, "No data directory to save to")
+                    }
+                }
+        }
+
+        if self.editing && board_changed {
+            self.puzzle.derive_rules_from_board();
+            gui.on_rules_changed(&self.puzzle);
+        }
+
+        if let Some((date, start_time, assisted)) = self.daily {
+            if self.puzzle.get_board().is_solved(self.puzzle.get_rules()) {
+                finish_daily(date, start_time.elapsed().as_secs(),
+                        assisted, self.write_files);
+                self.daily = None;
+            }
+        }
+
+        gui.draw_to_screen(&self.puzzle);
+
+        true
+    }
+
+    // True if the board may be thrown away: there is nothing on it, or
+    // the action was asked for again straight after the warning.
+    fn may_discard(&mut self, gui: &mut Gui,
+            confirm: Option<Discard>, action: Discard) -> bool {
+        if self.puzzle.get_board().is_blank() || confirm == Some(action) {
+            return true
+        }
+
+        self.confirm = Some(action);
+        report(gui, "This board has progress, do that again to discard it");
+        false
+    }
+
+    // Auto-crossing is part of the same step of the history.
+    fn update(&mut self, board: Board) {
+        if self.auto_cross && !self.editing {
+            let crossed = board.auto_cross(self.puzzle.get_rules());
+            self.puzzle.update(crossed);
+        } else {
+            self.puzzle.update(board);
+        }
+    }
+}
+
+// The first of untitled.txt, untitled-2.txt, ... that does not exist,
+// or None if there is no home directory.
+fn editor_path() -> Option<PathBuf> {
+    let mut path = paths::xdg_file(XdgDir::Data,
+            &format!("{}.{}", EDITOR_FILENAME, EDITOR_EXTENSION))?;
+    let mut n = 1;
+
+    while path.exists() {
+        n += 1;
+        path = paths::xdg_file(XdgDir::Data,
+                &format!("{}-{}.{}", EDITOR_FILENAME, n, EDITOR_EXTENSION))?;
+    }
+
+    Some(path)
+}
+
+// Print the message, and show it in the window for a while.
+fn report(gui: &mut Gui, msg: &str) {
+    println!("{}", msg);
+    gui.show_message(msg);
+}
+
+pub fn generate_random(opts: &GeneratorOptions, seed: u64) -> Option<Puzzle> {
+    let p = generator::generate(opts, seed);
+
+    if p.is_some() {
+        match opts.difficulty {
+            Some(d) => println!("Random {} x {} {} puzzle, seed {}",
+                    opts.width, opts.height, d.name(), seed),
+            None => println!("Random {} x {} puzzle, seed {}",
+                    opts.width, opts.height, seed)
+        }
+    } else {
+        println!("Could not generate a {} x {} puzzle with seed {}",
+                opts.width, opts.height, seed);
+    }
+
+    p
+}
+
+pub fn start_daily(date: Date) -> Option<Puzzle> {
+    let p = daily::daily_puzzle(date);
+
+    if p.is_some() {
+        println!("Daily puzzle for {}", date);
+        if let Some(best) = daily::best_time(date) {
+            println!("Best time so far: {}", format_time(best));
+        }
+    } else {
+        println!("Could not generate the daily puzzle for {}", date);
+    }
+
+    p
+}
+
+// Times are only recorded for puzzles solved without auto-fill.
+fn finish_daily(date: Date, seconds: u64, assisted: bool, write_files: bool) {
+    println!("Solved the daily puzzle for {} in {}", date, format_time(seconds));
+
+    if assisted {
+        println!("Auto-fill was used, so the time is not recorded");
+        return;
+    }
+    if !write_files {
+        return;
+    }
+
+    if let Some(best) = daily::best_time(date) {
+        println!("Previous best time: {}", format_time(best));
+    }
+
+    if let Err(e) = daily::record_time(date, seconds) {
+        println!("Could not record time: {}", e);
+    }
+}
+
+fn format_time(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,233 @@
+// generator.rs
+
+use std::time::{SystemTime,UNIX_EPOCH};
+
+use board::Board;
+use board::Tile;
+use puzzle::Puzzle;
+use solver;
+
+// Give up after this many random grids.
+const MAX_ATTEMPTS: u32 = 200;
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard
+}
+
+#[derive(Clone,Copy)]
+pub struct GeneratorOptions {
+    pub width: usize,
+    pub height: usize,
+
+    // Fraction of tiles that are filled, 0.0 to 1.0.
+    pub density: f64,
+
+    // None to accept any puzzle that can be solved by line logic.
+    pub difficulty: Option<Difficulty>
+}
+
+// xorshift64*, so that puzzles can be reproduced from their seed.
+pub struct Rng {
+    state: u64
+}
+
+impl Difficulty {
+    pub fn parse(s: &str) -> Option<Difficulty> {
+        match s {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            _ => None
+        }
+    }
+
+    // Rate a puzzle by how many rounds of line logic it takes,
+    // relative to its size.
+    pub fn from_passes(passes: u32, width: usize, height: usize) -> Difficulty {
+        let size = (width + height) as u32;
+
+        if 6 * passes <= size {
+            Difficulty::Easy
+        } else if 3 * passes <= size {
+            Difficulty::Medium
+        } else {
+            Difficulty::Hard
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard"
+        }
+    }
+}
+
+impl GeneratorOptions {
+    pub fn new(width: usize, height: usize) -> GeneratorOptions {
+        GeneratorOptions {
+            width,
+            height,
+            density: 0.6,
+            difficulty: None
+        }
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift gets stuck on zero
+        Rng { state: if seed == 0 { 0x9E3779B97F4A7C15 } else { seed } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in [0, n).
+    pub fn next_below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+pub fn random_seed() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() ^ ((d.subsec_nanos() as u64) << 32),
+        Err(_) => 0
+    }
+}
+
+// Generate a puzzle that can be solved by line logic alone.
+// Returns None if no suitable puzzle was found.
+pub fn generate(opts: &GeneratorOptions, seed: u64) -> Option<Puzzle> {
+    assert!(opts.width > 0 && opts.height > 0);
+    let mut rng = Rng::new(seed);
+
+    for _ in 0..MAX_ATTEMPTS {
+        let mut solution = random_board(opts, &mut rng);
+
+        // Repair the grid by toggling tiles that line logic can't
+        // determine until the puzzle becomes solvable.
+        for _ in 0..(opts.width * opts.height) {
+            let puzzle = Puzzle::from_solution(&solution);
+
+            let (b, passes) = match solver::line_solve(
+                    opts.width, opts.height, puzzle.get_rules()) {
+                Some(r) => r,
+                None => break
+            };
+
+            let undetermined = find_empty_tiles(&b);
+            if undetermined.is_empty() {
+                let d = Difficulty::from_passes(passes, opts.width, opts.height);
+                if opts.difficulty.is_none() || opts.difficulty == Some(d) {
+                    return Some(puzzle)
+                }
+                break;
+            }
+
+            let (x, y) = undetermined[rng.next_below(undetermined.len())];
+            let t = if solution.get(x, y) == Some(Tile::Filled) {
+                Tile::Empty
+            } else {
+                Tile::Filled
+            };
+            solution.set(x, y, t);
+        }
+    }
+
+    None
+}
+
+fn random_board(opts: &GeneratorOptions, rng: &mut Rng) -> Board {
+    let mut b = Board::new(opts.width, opts.height);
+
+    for y in 0..opts.height as u32 {
+        for x in 0..opts.width as u32 {
+            if rng.next_f64() < opts.density {
+                b.set(x, y, Tile::Filled);
+            }
+        }
+    }
+
+    b
+}
+
+fn find_empty_tiles(board: &Board) -> Vec<(u32, u32)> {
+    let mut tiles = Vec::new();
+
+    for y in 0..board.height as u32 {
+        for x in 0..board.width as u32 {
+            if board.get(x, y) == Some(Tile::Empty) {
+                tiles.push((x, y));
+            }
+        }
+    }
+
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::Solution;
+
+    #[test]
+    fn same_seed_same_puzzle() {
+        let opts = GeneratorOptions::new(10, 8);
+
+        for seed in 1..10 {
+            let a = generate(&opts, seed).unwrap();
+            let b = generate(&opts, seed).unwrap();
+            assert!(a.get_rules() == b.get_rules());
+        }
+
+        let a = generate(&opts, 1).unwrap();
+        let b = generate(&opts, 2).unwrap();
+        assert!(a.get_rules() != b.get_rules());
+    }
+
+    #[test]
+    fn solution_is_unique() {
+        for &(w, h) in [(5, 5), (10, 8), (15, 15)].iter() {
+            let opts = GeneratorOptions::new(w, h);
+
+            for seed in 1..10 {
+                let p = generate(&opts, seed).unwrap();
+                match solver::solve(w, h, p.get_rules()) {
+                    Solution::Unique(_) => {},
+                    _ => panic!("{}x{} seed {} is not unique", w, h, seed)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn difficulty_is_respected() {
+        let mut opts = GeneratorOptions::new(10, 10);
+        opts.difficulty = Some(Difficulty::Easy);
+
+        let mut found = 0;
+        for seed in 1..5 {
+            if let Some(p) = generate(&opts, seed) {
+                let (_, passes) = solver::line_solve(10, 10, p.get_rules()).unwrap();
+                assert!(Difficulty::from_passes(passes, 10, 10) == Difficulty::Easy);
+                found = found + 1;
+            }
+        }
+        assert!(found > 0);
+    }
+}
@@ -4,17 +4,21 @@ use std::collections::HashMap;
 use std::env;
 use std::path::Path;
 use sdl2::rect::Rect;
-use sdl2::render::{Renderer,Texture};
+
+use backend::{Atlas,Backend};
+use text;
 
 #[cfg(feature = "flic")]
 use flic;
-#[cfg(feature = "flic")]
+
+#[cfg(feature = "png")]
+use sdl2::image::LoadSurface;
+#[cfg(feature = "png")]
 use sdl2::pixels::PixelFormatEnum;
-#[cfg(feature = "flic")]
+#[cfg(feature = "png")]
 use sdl2::render::BlendMode;
-
 #[cfg(feature = "png")]
-use sdl2::image::LoadTexture;
+use sdl2::surface::Surface;
 
 #[derive(Clone,Copy)]
 pub enum Font {
@@ -41,7 +45,10 @@ pub enum Res {
     // Font(0..9)
     FontUnsolved(u8),
     FontSolved(u8),
-    FontConflict(u8)
+    FontConflict(u8),
+
+    // Glyph(' '..'~')
+    Glyph(u8)
 }
 
 pub const FONT_WIDTH: u32 = 7;
@@ -54,20 +61,13 @@ pub const TOOLBAR_PAINT_WIDTH: u32 = 13;
 pub const TOOLBAR_UNDO_REDO_WIDTH: u32 = 8;
 
 pub struct GfxLib<'a> {
-    pub renderer: Renderer<'a>,
-    texture: Texture,
+    pub backend: Box<dyn Backend + 'a>,
     lib: HashMap<Res, Rect>,
 }
 
 impl<'a> GfxLib<'a> {
-    pub fn new(renderer: Renderer<'a>) -> GfxLib<'a> {
-        let texture = match GfxLib::load_texture(&renderer) {
-            None => panic!("Error loading picross.flc or picross.png"),
-            Some(t) => t
-        };
-
+    pub fn new(backend: Box<dyn Backend + 'a>) -> GfxLib<'a> {
         let mut lib = HashMap::new();
-
         lib.insert(Res::ToolbarPicross,
                 Rect::new( 0,  0, TOOLBAR_BUTTON_WIDTH, TOOLBAR_BUTTON_HEIGHT));
         lib.insert(Res::ToolbarUndo,
@@ -107,27 +107,26 @@ impl<'a> GfxLib<'a> {
                     Rect::new(x, 70, FONT_WIDTH, FONT_HEIGHT));
         }
 
+        for c in text::FIRST_GLYPH..(text::LAST_GLYPH + 1) {
+            lib.insert(Res::Glyph(c), text::glyph_rect(c));
+        }
+
         GfxLib {
-            renderer: renderer,
-            texture: texture,
+            backend: backend,
             lib: lib
         }
     }
 
-    fn load_texture(renderer: &Renderer<'a>) -> Option<Texture> {
-        if let Some(t) = try_load_flic(renderer) {
-            return Some(t);
-        }
-        if let Some(t) = try_load_png(renderer) {
-            return Some(t);
+    pub fn draw(&mut self, res: Res, dst: Rect) {
+        if let Some(&src) = self.lib.get(&res) {
+            self.backend.draw_sprite(src, dst);
         }
-
-        None
     }
 
-    pub fn draw(&mut self, res: Res, dst: Rect) {
+    // For sizes that are not a whole multiple of the sprite.
+    pub fn draw_smooth(&mut self, res: Res, dst: Rect) {
         if let Some(&src) = self.lib.get(&res) {
-            let _ = self.renderer.copy(&self.texture, Some(src), Some(dst));
+            self.backend.draw_sprite_smooth(src, dst);
         }
     }
 
@@ -155,13 +154,29 @@ impl<'a> GfxLib<'a> {
             };
 
             if let Some(&src) = self.lib.get(&res) {
-                let _ = self.renderer.copy(&self.texture, Some(src), Some(dst));
+                self.backend.draw_sprite(src, dst);
             }
 
             x = x - font_spacing;
             n = n / 10;
         }
     }
+
+    // Any string, in the glyph font, from the top-left.
+    pub fn draw_text(&mut self, s: &str, scale: u32, x: i32, y: i32) {
+        let mut x = x;
+
+        for c in s.chars() {
+            let dst = Rect::new(x, y, scale * text::GLYPH_WIDTH, scale * text::GLYPH_HEIGHT);
+            self.draw(Res::Glyph(text::glyph_for(c)), dst);
+            x = x + (scale * text::GLYPH_ADVANCE) as i32;
+        }
+    }
+
+    pub fn draw_text_centre(&mut self, s: &str, scale: u32, xcentre: i32, y: i32) {
+        let w = text::text_width(s, scale) as i32;
+        self.draw_text(s, scale, xcentre - w / 2, y);
+    }
 }
 
 pub fn text_pixel_width(text: u32, scale: u32) -> u32 {
@@ -181,24 +196,37 @@ fn count_digits(text: u32) -> u32 {
     digits
 }
 
+// Load the sprite sheet from picross.flc or picross.png, and add the
+// glyphs for text.
+pub fn load_atlas() -> Option<Atlas> {
+    if let Some(a) = try_load_flic() {
+        return Some(text::add_glyph_sheet(a));
+    }
+    if let Some(a) = try_load_png() {
+        return Some(text::add_glyph_sheet(a));
+    }
+
+    None
+}
+
 /*--------------------------------------------------------------*/
 
 #[cfg(not(feature = "flic"))]
-fn try_load_flic(_: &Renderer) -> Option<Texture> {
+fn try_load_flic() -> Option<Atlas> {
     None
 }
 
 #[cfg(feature = "flic")]
-fn try_load_flic(renderer: &Renderer) -> Option<Texture> {
+fn try_load_flic() -> Option<Atlas> {
     let path = Path::new("resource/picross.flc");
-    if let Some(t) = try_load_flic2(renderer, &path) {
-        return Some(t);
+    if let Some(a) = try_load_flic2(&path) {
+        return Some(a);
     }
 
     if let Ok(mut path) = env::current_exe() {
         path.set_file_name("picross.flc");
-        if let Some(t) = try_load_flic2(renderer, &path) {
-            return Some(t);
+        if let Some(a) = try_load_flic2(&path) {
+            return Some(a);
         }
     }
 
@@ -206,7 +234,7 @@ fn try_load_flic(renderer: &Renderer) -> Option<Texture> {
 }
 
 #[cfg(feature = "flic")]
-fn try_load_flic2(renderer: &Renderer, path: &Path) -> Option<Texture> {
+fn try_load_flic2(path: &Path) -> Option<Atlas> {
     if let Ok(mut f) = flic::FlicFile::open(path) {
         let w = f.width() as usize;
         let h = f.height() as usize;
@@ -219,60 +247,63 @@ fn try_load_flic2(renderer: &Renderer, path: &Path) -> Option<Texture> {
             return None;
         }
 
-        let texture = renderer.create_texture_streaming(
-                PixelFormatEnum::ABGR8888, w as u32, h as u32);
-        if texture.is_err() {
-            return None;
+        let mut pixels = vec![0; 4 * w * h];
+        for (i, &c) in buf.iter().enumerate() {
+            let c = c as usize;
+            pixels[4 * i + 0] = pal[3 * c + 0];
+            pixels[4 * i + 1] = pal[3 * c + 1];
+            pixels[4 * i + 2] = pal[3 * c + 2];
+            pixels[4 * i + 3] = if c == 0 { 0 } else { 255 };
         }
 
-        let mut t = texture.unwrap();
-        render_to_texture(&mut t, w, h, &buf, &pal);
-        t.set_blend_mode(BlendMode::Blend);
-        return Some(t);
+        return Some(Atlas { width: w as u32, height: h as u32, pixels: pixels });
     }
 
     None
 }
 
-#[cfg(feature = "flic")]
-fn render_to_texture(
-        texture: &mut Texture,
-        w: usize, h: usize, buf: &[u8], pal: &[u8]) {
-    texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
-        for y in 0..h {
-            for x in 0..w {
-                let offset = pitch * y + 4 * x;
-                let c = buf[w * y + x] as usize;
-
-                buffer[offset + 0] = pal[3 * c + 0];
-                buffer[offset + 1] = pal[3 * c + 1];
-                buffer[offset + 2] = pal[3 * c + 2];
-                buffer[offset + 3] = if c == 0 { 0 } else { 255 };
-            }
-        }
-    }).unwrap();
-}
-
 /*--------------------------------------------------------------*/
 
 #[cfg(not(feature = "png"))]
-fn try_load_png(_: &Renderer) -> Option<Texture> {
+fn try_load_png() -> Option<Atlas> {
     None
 }
 
 #[cfg(feature = "png")]
-fn try_load_png(renderer: &Renderer) -> Option<Texture> {
+fn try_load_png() -> Option<Atlas> {
     let path = Path::new("resource/picross.png");
-    if let Ok(t) = renderer.load_texture(&path) {
-        return Some(t);
+    if let Some(a) = try_load_png2(&path) {
+        return Some(a);
     }
 
     if let Ok(mut path) = env::current_exe() {
         path.set_file_name("picross.png");
-        if let Ok(t) = renderer.load_texture(&path) {
-            return Some(t);
+        if let Some(a) = try_load_png2(&path) {
+            return Some(a);
         }
     }
 
     None
 }
+
+#[cfg(feature = "png")]
+fn try_load_png2(path: &Path) -> Option<Atlas> {
+    let mut src = Surface::from_file(path).ok()?;
+    let (w, h) = src.size();
+
+    // blit to convert into R, G, B, A bytes, keeping the alpha channel
+    let mut dst = Surface::new(w, h, PixelFormatEnum::ABGR8888).ok()?;
+    src.set_blend_mode(BlendMode::None).ok()?;
+    src.blit(None, &mut dst, None).ok()?;
+
+    let pitch = dst.pitch() as usize;
+    let pixels = dst.with_lock(|buffer: &[u8]| {
+        let mut pixels = Vec::with_capacity(4 * (w * h) as usize);
+        for y in 0..h as usize {
+            pixels.extend_from_slice(&buffer[pitch * y .. pitch * y + 4 * w as usize]);
+        }
+        pixels
+    });
+
+    Some(Atlas { width: w, height: h, pixels: pixels })
+}
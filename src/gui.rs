@@ -1,27 +1,34 @@
 // gui.rs
 
 use std::cmp::{max,min};
-use sdl2;
-use sdl2::EventPump;
-use sdl2::TimerSubsystem;
+use std::io;
 use sdl2::event::Event;
 use sdl2::event::WindowEvent;
-use sdl2::keyboard::Keycode;
+use sdl2::controller::Button;
+use sdl2::keyboard::{Keycode,Mod,LSHIFTMOD,RSHIFTMOD};
 use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::video::FullscreenType;
 
 use action::PicrossAction;
+use backend::Backend;
+use bindings::{Bindings,Command,Input};
 use board::Board;
 use board::Tile;
 use gfx::*;
+use puzzle::Clue;
 use puzzle::Puzzle;
+use puzzle::PuzzleReaderError;
 use puzzle::Rule;
 use puzzle::Rules;
-
-// FIXME - not sure what to import.
-const SDL_WINDOW_FULLSCREEN_DESKTOP: u32 = 0x1001;
+use replay::Recorder;
+use sdl_backend::SdlBackend;
+use settings::Settings;
+use solver;
+use text;
+use text::{GLYPH_HEIGHT,LINE_ADVANCE};
+use theme::{Theme,THEMES};
+use theme;
 
 const MIN_TOOLBAR_WIDTH: u32
     = 3
@@ -29,22 +36,54 @@ const MIN_TOOLBAR_WIDTH: u32
     + TOOLBAR_UNDO_REDO_WIDTH + 2 // redo
     + TOOLBAR_PAINT_WIDTH + 2 // palette
     + (TOOLBAR_PAINT_WIDTH - 1) * 2 + 1 // palette
+    + 3 + (TOOLBAR_PAINT_WIDTH + 2) * 3 // tools
     + 3;
 
-const DEFAULT_SCREEN_WIDTH: u32 = 640;
-const DEFAULT_SCREEN_HEIGHT: u32 = 400;
+const WINDOW_TITLE: &'static str = "Picross";
 const MIN_SCREEN_WIDTH: u32 = MIN_TOOLBAR_WIDTH;
 const MIN_SCREEN_HEIGHT: u32 = 128;
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 5.0;
+const ZOOM_STEP: f32 = 0.25;
+const MESSAGE_TICKS: u32 = 2500;
+const MAX_MESSAGE_LINES: usize = 4;
+const MAX_ERROR_CHARS: u32 = 48;
+
+// Touch input
+const LONG_PRESS_TICKS: u32 = 500;
+const TAP_SLOP: i32 = 8;
+const PINCH_STEP: f32 = 0.03;
+// SDL also turns touches into mouse events from this mouse
+const TOUCH_MOUSE_ID: u32 = 0xFFFFFFFF;
 
 // (w, h, toolbar_scale)
 type ScreenSize = (u32,u32,u32);
 
+#[derive(Clone,Copy,Eq,PartialEq)]
+enum Axis {
+    Horizontal,
+    Vertical
+}
+
+#[derive(Clone,Copy,Eq,PartialEq)]
+enum Tool {
+    Brush,
+    Line,
+    Rectangle
+}
+
 #[derive(Clone,Copy,Eq,PartialEq)]
 enum GuiMode {
     Neutral,
     HoldLMB,
     HoldRMB,
+    // painting a run of tiles with shift and the cursor keys
+    KeyPaint,
+    // painting a run of tiles with a controller button held
+    PadPaint(Tile),
     Pan,
+    // dragging the view around the minimap
+    MinimapPan,
 }
 
 enum WidgetType {
@@ -53,13 +92,12 @@ enum WidgetType {
     Redo,
 
     // Paint(tile,active,inactive)
-    Paint(Tile,Res,Res)
+    Paint(Tile,Res,Res),
+    Tool(Tool)
 }
 
 pub struct Gui<'a> {
     gfx: GfxLib<'a>,
-    timer: TimerSubsystem,
-    event_pump: EventPump,
     state: GuiState,
     widgets: Vec<Widget>,
 
@@ -67,17 +105,26 @@ pub struct Gui<'a> {
     last_redraw: u32,
 
     // Some(new screen size) if need to relayout the widgets
-    resize: Option<(u32,u32)>
+    resize: Option<(u32,u32)>,
+
+    recorder: Option<Recorder>,
+    error: Option<ErrorOverlay>,
+    message: Option<MessageOverlay>,
+    bindings: Bindings
 }
 
 struct GuiState {
     mode: GuiMode,
     selected_paint: Tile,
+    tool: Tool,
     board: Option<Board>,
+    // Some(board before the drag) while drawing a line or rectangle
+    drag_origin: Option<Board>,
     new_changes: bool,
 
     screen_size: ScreenSize,
-    board_scale: u32,
+    // MIN_ZOOM to MAX_ZOOM, in steps of ZOOM_STEP
+    board_scale: f32,
     offset_x: i32,
     offset_y: i32,
     board_pixel_width: u32,
@@ -86,9 +133,39 @@ struct GuiState {
     col_rule_max_pixel_height: u32,
     last_mouse_x: i32,
     last_mouse_y: i32,
+    mouse_x: i32,
+    mouse_y: i32,
+
+    // Drags are locked to the row or column of the first movement,
+    // unless ctrl is held.
+    free_paint: bool,
+    // Some(x,y) of the first tile painted by a drag
+    drag_start: Option<(u32,u32)>,
+    // Some(x,y) of the last tile painted by a drag
+    drag_last: Option<(u32,u32)>,
+    drag_axis: Option<Axis>,
 
     // Some(x,y) to highlight a row and a column
-    highlight: Option<(u32,u32)>
+    highlight: Option<(u32,u32)>,
+
+    // Some(x,y) once the cursor keys have been used
+    cursor: Option<(u32,u32)>,
+
+    // Some(clue) under the mouse
+    hover_clue: Option<Clue>,
+
+    show_minimap: bool,
+    theme: &'static Theme,
+    // show clue states by shape as well as colour
+    clue_markers: bool,
+
+    // Some(touch) while a single finger is down and could be a tap
+    touch: Option<Touch>,
+    fingers: u32,
+    // Some(x,y) of the middle of the last two finger gesture
+    gesture_centre: Option<(i32,i32)>,
+    // pinch distance not yet turned into zoom
+    pinch: f32
 }
 
 struct Widget {
@@ -96,41 +173,128 @@ struct Widget {
     rect: Rect,
 }
 
-impl<'a> Gui<'a> {
-    pub fn new() -> Gui<'a> {
-        let sdl = sdl2::init().unwrap();
-        let video = sdl.video().unwrap();
+// A finger that went down, in screen pixels.
+#[derive(Clone,Copy)]
+struct Touch {
+    finger_id: i64,
+    x: i32,
+    y: i32,
+    ticks: u32
+}
+
+// Shown over the board when a dropped file could not be loaded,
+// until the next key press or click.
+struct ErrorOverlay {
+    // Some(line number, column) of the problem
+    position: Option<(usize,usize)>,
+    message: String
+}
 
+// Recent messages, shown above the toolbar for a while.
+struct MessageOverlay {
+    lines: Vec<String>,
+    // ticks when the messages go
+    until: u32
+}
+
+impl<'a> Gui<'a> {
+    pub fn new(settings: &Settings) -> Gui<'a> {
         init_png();
 
-        let state = GuiState::new(DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT);
-        let screen_size = state.screen_size;
+        let atlas = match load_atlas() {
+            None => panic!("Error loading picross.flc or picross.png"),
+            Some(a) => a
+        };
+
+        let (screen_w, screen_h) = settings.window_size;
+        let backend = SdlBackend::new(WINDOW_TITLE,
+                max(screen_w, MIN_SCREEN_WIDTH), max(screen_h, MIN_SCREEN_HEIGHT),
+                MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT, &atlas);
+
+        let mut gui = Gui::new_with_backend(Box::new(backend));
+        gui.apply_settings(settings);
+        gui
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        let zoom = (settings.zoom / ZOOM_STEP).round() * ZOOM_STEP;
 
-        let mut window
-            = video.window("Picross", DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT)
-            .resizable()
-            .position_centered()
-            .opengl()
-            .build().unwrap();
+        self.state.board_scale = zoom.max(MIN_ZOOM).min(MAX_ZOOM);
+        self.state.selected_paint = settings.paint;
+        self.state.show_minimap = settings.minimap;
+        self.state.clue_markers = settings.clue_markers;
 
-        let _ = window.set_minimum_size(MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT);
+        if let Some(t) = theme::find_theme(&settings.theme) {
+            self.set_theme(t);
+        }
 
-        let renderer = window.renderer().build().unwrap();
-        let timer = sdl.timer().unwrap();
-        let event_pump = sdl.event_pump().unwrap();
+        if settings.fullscreen != self.gfx.backend.is_fullscreen() {
+            self.gfx.backend.toggle_fullscreen();
+        }
+    }
+
+    // The window size is only kept when not fullscreen.
+    pub fn store_settings(&self, settings: &mut Settings) {
+        settings.fullscreen = self.gfx.backend.is_fullscreen();
+        if !settings.fullscreen {
+            settings.window_size = self.gfx.backend.size();
+        }
+
+        settings.zoom = self.state.board_scale;
+        settings.paint = self.state.selected_paint;
+        settings.minimap = self.state.show_minimap;
+        settings.clue_markers = self.state.clue_markers;
+        settings.theme = self.state.theme.name.to_string();
+    }
+
+    pub fn new_with_backend(backend: Box<dyn Backend + 'a>) -> Gui<'a> {
+        let (screen_w, screen_h) = backend.size();
+        let state = GuiState::new(screen_w, screen_h);
+        let screen_size = state.screen_size;
 
         Gui {
-            gfx: GfxLib::new(renderer),
-            timer: timer,
-            event_pump: event_pump,
+            gfx: GfxLib::new(backend),
             state: state,
             widgets: Gui::make_widgets(screen_size),
             redraw: true,
             last_redraw: 0,
-            resize: None
+            resize: None,
+            recorder: None,
+            error: None,
+            message: None,
+            bindings: Bindings::new()
         }
     }
 
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.bindings = bindings;
+    }
+
+    pub fn set_theme(&mut self, t: &'static Theme) {
+        self.state.theme = t;
+        self.gfx.backend.recolour_sprites(&theme::sprite_colours(t));
+        self.redraw = true;
+    }
+
+    fn next_theme(&mut self) {
+        let t = theme::next_theme(self.state.theme);
+        self.set_theme(t);
+        let msg = format!("Theme: {}", t.name);
+        println!("{}", msg);
+        self.show_message(&msg);
+    }
+
+    // Record the events read from now on.
+    pub fn record_to(&mut self, filename: &String) -> io::Result<()> {
+        let (screen_w, screen_h) = self.gfx.backend.size();
+        self.recorder = Some(Recorder::create(filename, screen_w, screen_h)?);
+        Ok(())
+    }
+
+    pub fn request_redraw(&mut self) {
+        self.redraw = true;
+    }
+
     fn is_picross_label_visible(screen_size: ScreenSize) -> bool {
         let (screen_w, _, toolbar_scale) = screen_size;
         let toolbar_w = MIN_TOOLBAR_WIDTH + TOOLBAR_BUTTON_WIDTH + 3; // picross
@@ -144,6 +308,7 @@ impl<'a> Gui<'a> {
 
         let paint_spacing = TOOLBAR_PAINT_WIDTH - 1;
         let palette_width = toolbar_scale * (TOOLBAR_PAINT_WIDTH + 2 + paint_spacing * 2 + 1);
+        let tools_width = toolbar_scale * (3 + (TOOLBAR_PAINT_WIDTH + 2) * 2 + TOOLBAR_PAINT_WIDTH);
 
         let label_visible = Gui::is_picross_label_visible(screen_size);
         let x_undo =
@@ -153,8 +318,10 @@ impl<'a> Gui<'a> {
                 (toolbar_scale * 3) as i32
             };
         let x_redo = x_undo + (toolbar_scale * (TOOLBAR_UNDO_REDO_WIDTH + 2)) as i32;
+        // the palette and tools are centred together, so the tools stay
+        // on screen in a narrow window
         let x_palette = max(x_redo + (toolbar_scale * (TOOLBAR_UNDO_REDO_WIDTH + 2)) as i32,
-                            (screen_w - palette_width) as i32 / 2);
+                            (screen_w as i32 - (palette_width + tools_width) as i32) / 2);
 
         // label
         if label_visible {
@@ -210,6 +377,19 @@ impl<'a> Gui<'a> {
                         toolbar_scale * TOOLBAR_BUTTON_HEIGHT),
                 });
 
+        // tools
+        let x_tools = x_palette + (palette_width + toolbar_scale * 3) as i32;
+        for (i, &tool) in [Tool::Brush, Tool::Line, Tool::Rectangle].iter().enumerate() {
+            ws.push(Widget {
+                    mode: WidgetType::Tool(tool),
+                    rect: Rect::new(
+                            x_tools + (toolbar_scale * (TOOLBAR_PAINT_WIDTH + 2) * i as u32) as i32,
+                            y,
+                            toolbar_scale * TOOLBAR_PAINT_WIDTH,
+                            toolbar_scale * TOOLBAR_BUTTON_HEIGHT),
+                    });
+        }
+
         ws
     }
 
@@ -217,8 +397,54 @@ impl<'a> Gui<'a> {
         self.state.on_new_puzzle(puzzle);
     }
 
-    pub fn read_input(&mut self, board: &Board) -> PicrossAction {
-        let curr_ticks = self.timer.ticks();
+    pub fn on_rules_changed(&mut self, puzzle: &Puzzle) {
+        self.state.calc_rule_pixel_sizes(puzzle);
+    }
+
+    // The full message goes in the window title.
+    pub fn on_load_error(&mut self, filename: &String, err: &PuzzleReaderError) {
+        let title = format!("{} - {}: {}", WINDOW_TITLE, filename, err);
+        self.gfx.backend.set_title(&title);
+        self.error = Some(ErrorOverlay {
+            position: err.position(),
+            message: format!("{}: {}", filename, err)
+        });
+        self.redraw = true;
+    }
+
+    // Show a line of text for a few seconds, below any recent ones.
+    pub fn show_message(&mut self, msg: &str) {
+        let until = self.gfx.backend.ticks() + MESSAGE_TICKS;
+
+        match self.message {
+            Some(ref mut m) => {
+                m.lines.push(msg.to_string());
+                if m.lines.len() > MAX_MESSAGE_LINES {
+                    m.lines.remove(0);
+                }
+                m.until = until;
+            },
+            None =>
+                self.message = Some(MessageOverlay {
+                    lines: vec![msg.to_string()],
+                    until: until
+                })
+        }
+        self.redraw = true;
+    }
+
+    fn dismiss_error(&mut self) {
+        self.gfx.backend.set_title(WINDOW_TITLE);
+        self.error = None;
+        self.redraw = true;
+    }
+
+    pub fn read_input(&mut self, puzzle: &Puzzle) -> PicrossAction {
+        let board = puzzle.get_board();
+        let curr_ticks = self.gfx.backend.ticks();
+        if let Some(action) = self.state.on_long_press(board, curr_ticks) {
+            return action
+        }
         if curr_ticks >= self.last_redraw + 1000 / 60 {
             self.redraw = true;
             return PicrossAction::NoOp;
@@ -228,7 +454,34 @@ impl<'a> Gui<'a> {
         let toolbar_y = (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32;
 
         let timeout = self.last_redraw + 1000 / 60 - curr_ticks;
-        if let Some(e) = self.event_pump.wait_event_timeout(timeout) {
+        if let Some(e) = self.gfx.backend.poll_event(timeout) {
+            if let Some(mut r) = self.recorder.take() {
+                match r.record(self.gfx.backend.ticks(), &e) {
+                    Ok(_) => self.recorder = Some(r),
+                    Err(e) => println!("Stopped recording: {}", e)
+                }
+            }
+
+            if self.error.is_some() {
+                match e {
+                    Event::KeyDown {..} | Event::MouseButtonDown {..} => {
+                        self.dismiss_error();
+                        return PicrossAction::NoOp
+                    },
+                    _ => {}
+                }
+            }
+
+            match e {
+                // the finger events are used instead
+                Event::MouseMotion { which: TOUCH_MOUSE_ID, .. }
+                | Event::MouseButtonDown { which: TOUCH_MOUSE_ID, .. }
+                | Event::MouseButtonUp { which: TOUCH_MOUSE_ID, .. } =>
+                    return PicrossAction::NoOp,
+
+                _ => {}
+            }
+
             match e {
                 Event::Quit {..} =>
                     return PicrossAction::Quit,
@@ -236,41 +489,50 @@ impl<'a> Gui<'a> {
                 Event::Window { win_event: WindowEvent::Resized(data1, data2), .. } =>
                     self.resize = Some((data1 as u32, data2 as u32)),
 
-                Event::KeyDown { keycode: Some(Keycode::F), .. }
-                | Event::KeyDown { keycode: Some(Keycode::F11), .. } => {
-                    self.toggle_fullscreen();
-                    return PicrossAction::NoOp
-                },
+                Event::Window { win_event: WindowEvent::FocusLost, .. } =>
+                    return self.state.on_focus_lost(),
 
-                Event::KeyDown { keycode: Some(k), .. } =>
-                    return self.state.on_key_down(k),
+                Event::KeyDown { keycode: Some(k), keymod, .. } => {
+                    let command = self.bindings.get(Input::Key(k));
+                    if command == Some(Command::ToggleFullscreen) {
+                        self.gfx.backend.toggle_fullscreen();
+                        return PicrossAction::NoOp
+                    }
+                    if command == Some(Command::NextTheme) {
+                        self.next_theme();
+                        return PicrossAction::NoOp
+                    }
+                    return self.state.on_key_down(board, k, command, keymod)
+                },
 
-                Event::MouseMotion { x, y, .. } =>
-                    return self.state.on_mouse_motion(board, x, y),
+                Event::KeyUp { keycode: Some(k), .. } =>
+                    return self.state.on_key_up(k),
 
-                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
-                    let w = Gui::find_widget(&self.widgets, x, y);
-                    if y < toolbar_y || w.is_some() {
-                        return self.state.on_lmb(board, w, x, y)
-                    }
+                Event::MouseMotion { x, y, .. } => {
+                    self.state.hover_clue = self.state.find_clue(puzzle.get_rules(), x, y);
+                    return self.state.on_mouse_motion(board, x, y)
                 },
 
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } =>
+                    return self.on_lmb(puzzle, x, y),
+
                 Event::MouseButtonDown { mouse_btn: MouseButton::Right, x, y, .. } =>
                     if y < toolbar_y {
-                        return self.state.on_rmb(board, x, y)
+                        return self.on_rmb(board, x, y)
                     },
 
                 Event::MouseButtonDown { mouse_btn: MouseButton::Middle, x, y, .. } =>
                     return self.state.on_mmb(x, y),
 
-                Event::MouseButtonDown { mouse_btn: MouseButton::X1, .. } =>
-                    if self.state.mode == GuiMode::Neutral {
-                        return PicrossAction::Undo
-                    },
-
-                Event::MouseButtonDown { mouse_btn: MouseButton::X2, .. } =>
-                    if self.state.mode == GuiMode::Neutral {
-                        return PicrossAction::Redo
+                Event::MouseButtonDown { mouse_btn, .. } =>
+                    match self.bindings.get(Input::Mouse(mouse_btn)) {
+                        Some(Command::ToggleFullscreen) =>
+                            self.gfx.backend.toggle_fullscreen(),
+                        Some(Command::NextTheme) =>
+                            self.next_theme(),
+                        Some(command) =>
+                            return self.state.on_command(board, command, false),
+                        None => {}
                     },
 
                 Event::MouseButtonUp { mouse_btn: MouseButton::Left, .. } =>
@@ -285,6 +547,36 @@ impl<'a> Gui<'a> {
                 Event::MouseWheel { y, .. } =>
                     return self.state.on_wheel(y),
 
+                Event::FingerDown { finger_id, x, y, .. } => {
+                    let (fx, fy) = self.state.finger_coord(x, y);
+                    self.state.on_finger_down(finger_id, fx, fy, curr_ticks);
+                },
+
+                Event::FingerMotion { finger_id, x, y, .. } => {
+                    let (fx, fy) = self.state.finger_coord(x, y);
+                    self.state.on_finger_motion(finger_id, fx, fy);
+                },
+
+                // a tap is a click of the left button
+                Event::FingerUp { finger_id, .. } =>
+                    if let Some((x, y)) = self.state.on_finger_up(finger_id) {
+                        return match self.on_lmb(puzzle, x, y) {
+                            PicrossAction::NoOp => self.state.on_lmb_up(),
+                            action => action
+                        }
+                    },
+
+                Event::ControllerButtonDown { button, .. } =>
+                    return self.state.on_pad_button_down(board, button),
+
+                Event::ControllerButtonUp { button, .. } =>
+                    return self.state.on_pad_button_up(button),
+
+                Event::MultiGesture { num_fingers, d_dist, x, y, .. } => {
+                    let (fx, fy) = self.state.finger_coord(x, y);
+                    return self.state.on_gesture(num_fingers, d_dist, fx, fy)
+                },
+
                 Event::DropFile { filename, .. } => {
                     self.state.mode = GuiMode::Neutral;
                     self.state.board = None;
@@ -299,6 +591,45 @@ impl<'a> Gui<'a> {
         PicrossAction::NoOp
     }
 
+    fn on_lmb(&mut self, puzzle: &Puzzle, x: i32, y: i32) -> PicrossAction {
+        let board = puzzle.get_board();
+        let (_, screen_h, toolbar_scale) = self.state.screen_size;
+        let toolbar_y = (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32;
+
+        if self.state.mode == GuiMode::Neutral && self.state.in_minimap(board, x, y) {
+            return self.state.on_minimap_press(board, x, y)
+        }
+
+        if self.state.mode == GuiMode::Neutral && y < toolbar_y {
+            if let Some(clue) = self.state.find_clue(puzzle.get_rules(), x, y) {
+                return PicrossAction::ToggleClueDone(clue)
+            }
+        }
+
+        if y < toolbar_y && self.state.on_pinned_clues(x, y) {
+            return PicrossAction::NoOp
+        }
+
+        let w = Gui::find_widget(&self.widgets, x, y);
+        if y < toolbar_y || w.is_some() {
+            return self.state.on_lmb(board, w, x, y)
+        }
+
+        PicrossAction::NoOp
+    }
+
+    // The minimap is above the board, so it gets either button first.
+    fn on_rmb(&mut self, board: &Board, x: i32, y: i32) -> PicrossAction {
+        if self.state.mode == GuiMode::Neutral && self.state.in_minimap(board, x, y) {
+            return self.state.on_minimap_press(board, x, y)
+        }
+        if self.state.on_pinned_clues(x, y) {
+            return PicrossAction::NoOp
+        }
+
+        self.state.on_rmb(board, x, y)
+    }
+
     fn find_widget(widgets: &Vec<Widget>, x: i32, y: i32) -> Option<&Widget> {
         widgets.iter().find(|w| {
                 let r = &w.rect;
@@ -306,21 +637,20 @@ impl<'a> Gui<'a> {
                 && r.y() <= y && y <= r.y() + (r.height() as i32) })
     }
 
-    fn toggle_fullscreen(&mut self) {
-        let mut window = self.gfx.renderer.window_mut().unwrap();
-
-        if window.window_flags() & SDL_WINDOW_FULLSCREEN_DESKTOP != 0 {
-            window.set_fullscreen(FullscreenType::Off).unwrap();
-        } else {
-            window.set_fullscreen(FullscreenType::Desktop).unwrap();
-        }
-    }
-
-    pub fn draw_to_screen(&mut self, rules: Rules, board: &Board) {
+    pub fn draw_to_screen(&mut self, puzzle: &Puzzle) {
+        let board = puzzle.get_board();
         if !self.redraw {
             return;
         }
 
+        let expired = match self.message {
+            Some(ref m) => self.gfx.backend.ticks() >= m.until,
+            None => false
+        };
+        if expired {
+            self.message = None;
+        }
+
         if let Some((new_w, new_h)) = self.resize {
             self.state.on_resize_window(new_w, new_h);
             self.widgets = Gui::make_widgets(self.state.screen_size);
@@ -328,10 +658,7 @@ impl<'a> Gui<'a> {
         }
 
         let (screen_w, screen_h, toolbar_scale) = self.state.screen_size;
-        let colour_white = Color::RGB(0xD0, 0xD0, 0xD0);
-        let colour_light_grey = Color::RGB(0x98, 0x98, 0x98);
-        let colour_dark_grey = Color::RGB(0x58, 0x58, 0x58);
-        let colour_rose = Color::RGB(0xC2, 0xBC, 0xBC);
+        let theme = self.state.theme;
 
         let toolbar_rect = Rect::new(
                 0,
@@ -339,113 +666,207 @@ impl<'a> Gui<'a> {
                 screen_w,
                 toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6));
 
-        self.gfx.renderer.set_draw_color(colour_white);
-        self.gfx.renderer.clear();
+        self.gfx.backend.clear(theme.background);
 
         // board
         if let Some((x, y)) = self.state.highlight {
-            self.gfx.renderer.set_draw_color(colour_rose);
-            Gui::draw_board_line(&mut self.gfx, &self.state,
+            Gui::draw_board_line(&mut self.gfx, &self.state, theme.highlight,
                     x, 0, x + 1, board.height as u32);
-            Gui::draw_board_line(&mut self.gfx, &self.state,
+            Gui::draw_board_line(&mut self.gfx, &self.state, theme.highlight,
                     0, y, board.width as u32, y + 1);
         }
 
-        self.gfx.renderer.set_draw_color(colour_light_grey);
         for y in 0..(board.height + 1) as u32 {
-            Gui::draw_board_line(&mut self.gfx, &self.state,
+            Gui::draw_board_line(&mut self.gfx, &self.state, theme.grid,
                     0, y, board.width as u32, y);
         }
 
         for x in 0..(board.width + 1) as u32 {
-            Gui::draw_board_line(&mut self.gfx, &self.state,
+            Gui::draw_board_line(&mut self.gfx, &self.state, theme.grid,
                     x, 0, x, board.height as u32);
         }
 
-        self.gfx.renderer.set_draw_color(colour_dark_grey);
         for y in 0..(board.height + 1) as u32 {
             if y % 5 == 0 || y == board.height as u32 {
-                Gui::draw_board_line(&mut self.gfx, &self.state,
+                Gui::draw_board_line(&mut self.gfx, &self.state, theme.grid_major,
                         0, y, board.width as u32, y);
             }
         }
 
         for x in 0..(board.width + 1) as u32 {
             if x % 5 == 0 || x == board.width as u32 {
-                Gui::draw_board_line(&mut self.gfx, &self.state,
+                Gui::draw_board_line(&mut self.gfx, &self.state, theme.grid_major,
                         x, 0, x, board.height as u32);
             }
         }
 
-        if let Some(ref b) = self.state.board {
-            Gui::draw_rules(&mut self.gfx, &self.state, rules, b);
+        {
+            let b = self.state.board.as_ref().unwrap_or(board);
             Gui::draw_board(&mut self.gfx, &self.state, b);
-        } else {
-            Gui::draw_rules(&mut self.gfx, &self.state, rules, board);
-            Gui::draw_board(&mut self.gfx, &self.state, board);
+
+            if self.state.mode == GuiMode::Neutral {
+                if let Some(clue) = self.state.hover_clue {
+                    Gui::draw_block_range(&mut self.gfx, &self.state, puzzle.get_rules(), b, clue);
+                }
+            }
+
+            // after the board, which scrolls beneath pinned clues
+            Gui::draw_rules(&mut self.gfx, &self.state, puzzle, b);
+
+            if let Some(rect) = self.state.minimap_rect(b) {
+                Gui::draw_minimap(&mut self.gfx, &self.state, b, rect);
+            }
         }
 
         // toolbar
-        self.gfx.renderer.set_draw_color(colour_light_grey);
-        let _ = self.gfx.renderer.fill_rect(toolbar_rect);
-
-        self.gfx.renderer.set_draw_color(colour_dark_grey);
-        let _ = self.gfx.renderer.draw_rect(toolbar_rect);
+        self.gfx.backend.fill_rect(theme.toolbar, toolbar_rect);
+        self.gfx.backend.draw_rect(theme.foreground, toolbar_rect);
 
         // widgets
         for w in self.widgets.iter() {
             Gui::draw_widget(&mut self.gfx, &self.state, w);
         }
 
-        self.gfx.renderer.present();
+        if let Some(length) = self.state.drag_length() {
+            Gui::draw_drag_length(&mut self.gfx, &self.state, length);
+        }
+
+        if let Some(ref message) = self.message {
+            Gui::draw_message(&mut self.gfx, &self.state, message);
+        }
+
+        if let Some(ref error) = self.error {
+            Gui::draw_error(&mut self.gfx, &self.state, error);
+        }
+
+        self.gfx.backend.present();
         self.redraw = false;
-        self.last_redraw = self.timer.ticks();
+        self.last_redraw = self.gfx.backend.ticks();
     }
 
     fn draw_rules(gfx: &mut GfxLib<'a>, state: &GuiState,
-            rules: Rules, board: &Board) {
-        let scale = state.board_scale;
-        let text_scale = min(2, scale);
-        let (col_rules, row_rules) = rules;
-        let x_spacing = (text_scale * 5) as i32;
-        let y_spacing = (text_scale * (FONT_HEIGHT + 2)) as i32;
+            puzzle: &Puzzle, board: &Board) {
+        let text_scale = state.text_scale();
+        let (col_rules, row_rules) = puzzle.get_rules();
+        let theme = state.theme;
+
+        let col_heads: Vec<Vec<u32>> = (0..board.width)
+            .map(|x| board.get_completed_column_segments_from_head(x)).collect();
+        let col_tails: Vec<Vec<u32>> = (0..board.width)
+            .map(|x| board.get_completed_column_segments_from_tail(x)).collect();
+        let row_heads: Vec<Vec<u32>> = (0..board.height)
+            .map(|y| board.get_completed_row_segments_from_head(y)).collect();
+        let row_tails: Vec<Vec<u32>> = (0..board.height)
+            .map(|y| board.get_completed_row_segments_from_tail(y)).collect();
+
+        // clues pinned to the edges of the screen cover the board
+        let (screen_w, screen_h, toolbar_scale) = state.screen_size;
+        let canvas_h = screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6);
+        let (edge_x, edge_y) = state.clue_edges();
 
-        let mut x = state.offset_x + (scale * TILE_WIDTH / 2 + 1) as i32;
-        for (col, rule) in col_rules.iter().enumerate() {
-            let len = rule.len();
-            let head = board.get_completed_column_segments_from_head(col);
-            let tail = board.get_completed_column_segments_from_tail(col);
-            let mut y = state.offset_y - (scale * 4 + text_scale * FONT_HEIGHT) as i32;
+        if state.offset_y < edge_y {
+            gfx.backend.fill_rect(theme.clue_shade, Rect::new(0, 0, screen_w, edge_y as u32));
+            gfx.backend.fill_rect(theme.foreground,
+                    Rect::new(0, edge_y - text_scale as i32, screen_w, text_scale));
+        }
+        if state.offset_x < edge_x {
+            gfx.backend.fill_rect(theme.clue_shade, Rect::new(0, 0, edge_x as u32, canvas_h));
+            gfx.backend.fill_rect(theme.foreground,
+                    Rect::new(edge_x - text_scale as i32, 0, text_scale, canvas_h));
+        }
 
-            for i in 0..len {
-                let revi = len - i - 1;
-                let v = rule[revi];
-                let font = Gui::pick_font(v, len, &head, revi, &tail, i);
+        for (clue, v, rect) in state.clue_rects(puzzle.get_rules()) {
+            let font = match clue {
+                Clue::Column(x, i) => {
+                    let len = col_rules[x].len();
+                    Gui::pick_font(v, len, &col_heads[x], i, &col_tails[x], len - i - 1)
+                },
+                Clue::Row(y, i) => {
+                    let len = row_rules[y].len();
+                    Gui::pick_font(v, len, &row_heads[y], i, &row_tails[y], len - i - 1)
+                }
+            };
 
-                gfx.text_centre(font, v, text_scale, x, y);
-                y = y - y_spacing;
+            let border = Rect::new(
+                    rect.x() - text_scale as i32, rect.y() - text_scale as i32,
+                    rect.width() + 2 * text_scale, rect.height() + 2 * text_scale);
+
+            if state.hover_clue == Some(clue) {
+                gfx.backend.fill_rect(theme.highlight, border);
             }
 
-            x = x + (scale * (TILE_WIDTH + 2)) as i32;
+            if state.clue_markers {
+                Gui::draw_marked_clue(gfx, state, font, v, rect, border);
+            } else {
+                gfx.text_right(font, v, text_scale, rect.x() + rect.width() as i32, rect.y());
+            }
+
+            if puzzle.is_done(clue) {
+                let strike = Rect::new(
+                        border.x(),
+                        rect.y() + (rect.height() / 2) as i32 - (text_scale / 2) as i32,
+                        border.width(), text_scale);
+                gfx.backend.fill_rect(theme.foreground, strike);
+            }
         }
+    }
 
-        let mut y = state.offset_y + (scale * TILE_HEIGHT - text_scale * FONT_HEIGHT) as i32 / 2;
-        for (row, rule) in row_rules.iter().enumerate() {
-            let len = rule.len();
-            let head = board.get_completed_row_segments_from_head(row);
-            let tail = board.get_completed_row_segments_from_tail(row);
-            let mut x = state.offset_x - (scale * 4) as i32;
+    // For colour blindness, the clue is drawn in one colour and its state
+    // is shown by shape: solved clues are dimmed and conflicts are boxed.
+    fn draw_marked_clue(gfx: &mut GfxLib<'a>, state: &GuiState,
+            font: Font, v: u32, rect: Rect, border: Rect) {
+        let text_scale = state.text_scale();
+        let theme = state.theme;
+
+        gfx.text_right(Font::Unsolved, v, text_scale, rect.x() + rect.width() as i32, rect.y());
+
+        match font {
+            Font::Solved => {
+                let (r, g, b, _) = theme.background.rgba();
+                gfx.backend.fill_rect(Color::RGBA(r, g, b, 0xA0), rect);
+            },
+
+            Font::Conflict =>
+                for i in 0..text_scale {
+                    gfx.backend.draw_rect(theme.foreground, Rect::new(
+                            border.x() + i as i32, border.y() + i as i32,
+                            border.width() - 2 * i, border.height() - 2 * i));
+                },
 
-            for i in 0..len {
-                let revi = len - i - 1;
-                let v = rule[revi];
-                let font = Gui::pick_font(v, len, &head, revi, &tail, i);
+            Font::Unsolved => {}
+        }
+    }
 
-                gfx.text_right(font, v, text_scale, x, y);
-                x = x - x_spacing - text_pixel_width(v, text_scale) as i32;
-            }
+    // Shade the tiles where the block of the clue could go.
+    fn draw_block_range(gfx: &mut GfxLib<'a>, state: &GuiState,
+            rules: Rules, board: &Board, clue: Clue) {
+        let (col_rules, row_rules) = rules;
+        let x_spacing = state.scaled(TILE_WIDTH + 2);
+        let y_spacing = state.scaled(TILE_HEIGHT + 2);
+        let line_w = state.scaled(2);
+
+        let rect = match clue {
+            Clue::Column(x, i) if x < board.width && x < col_rules.len() =>
+                solver::block_range(&board.get_column(x), &col_rules[x], i).map(|(first, last)|
+                    Rect::new(
+                        state.offset_x + (x_spacing * x as u32) as i32,
+                        state.offset_y + (y_spacing * first as u32) as i32,
+                        x_spacing - line_w,
+                        y_spacing * (last - first + 1) as u32 - line_w)),
+
+            Clue::Row(y, i) if y < board.height && y < row_rules.len() =>
+                solver::block_range(&board.get_row(y), &row_rules[y], i).map(|(first, last)|
+                    Rect::new(
+                        state.offset_x + (x_spacing * first as u32) as i32,
+                        state.offset_y + (y_spacing * y as u32) as i32,
+                        x_spacing * (last - first + 1) as u32 - line_w,
+                        y_spacing - line_w)),
+
+            _ => None
+        };
 
-            y = y + (scale * (TILE_HEIGHT + 2)) as i32;
+        if let Some(r) = rect {
+            gfx.backend.fill_rect(state.theme.block_range, r);
         }
     }
 
@@ -477,8 +898,9 @@ impl<'a> Gui<'a> {
         let (screen_w, screen_h, toolbar_scale) = state.screen_size;
         let board_w = screen_w as i32;
         let board_h = (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32;
-        let x_spacing = state.board_scale * (TILE_WIDTH + 2);
-        let y_spacing = state.board_scale * (TILE_HEIGHT + 2);
+        let x_spacing = state.scaled(TILE_WIDTH + 2);
+        let y_spacing = state.scaled(TILE_HEIGHT + 2);
+        let line_w = state.scaled(2);
 
         let xmin = max(0, (2 - state.offset_x) / (x_spacing as i32)) as u32;
         let ymin = max(0, (2 - state.offset_y) / (y_spacing as i32)) as u32;
@@ -503,29 +925,196 @@ impl<'a> Gui<'a> {
                 let rect = Rect::new(
                         state.offset_x + (x_spacing * x) as i32,
                         state.offset_y + (y_spacing * y) as i32,
-                        state.board_scale * TILE_WIDTH,
-                        state.board_scale * TILE_HEIGHT);
+                        x_spacing - line_w,
+                        y_spacing - line_w);
+
+                if state.board_scale.fract() == 0.0 {
+                    gfx.draw(res, rect);
+                } else {
+                    gfx.draw_smooth(res, rect);
+                }
+            }
+        }
+
+        // keyboard cursor, over the grid lines around the tile
+        if let Some((x, y)) = state.cursor {
+            let border = line_w;
+
+            for i in 0..border {
+                let rect = Rect::new(
+                        state.offset_x + (x_spacing * x) as i32 - (border - i) as i32,
+                        state.offset_y + (y_spacing * y) as i32 - (border - i) as i32,
+                        x_spacing - line_w + 2 * (border - i),
+                        y_spacing - line_w + 2 * (border - i));
 
-                gfx.draw(res, rect);
+                gfx.backend.draw_rect(state.theme.active, rect);
             }
         }
     }
 
-    fn draw_board_line(gfx: &mut GfxLib, state: &GuiState,
+    fn draw_board_line(gfx: &mut GfxLib, state: &GuiState, colour: Color,
             x1: u32, y1: u32, x2: u32, y2: u32) {
         let board_x = state.offset_x;
         let board_y = state.offset_y;
-        let scale = state.board_scale;
-        let board_x_spacing = TILE_WIDTH + 2;
-        let board_y_spacing = TILE_HEIGHT + 2;
+        let board_x_spacing = state.scaled(TILE_WIDTH + 2);
+        let board_y_spacing = state.scaled(TILE_HEIGHT + 2);
+        let line_w = state.scaled(2);
 
         let line = Rect::new(
-                board_x + (scale as i32) * ((board_x_spacing * x1) as i32 - 2),
-                board_y + (scale as i32) * ((board_y_spacing * y1) as i32 - 2),
-                scale * (2 + board_x_spacing * (x2 - x1)),
-                scale * (2 + board_y_spacing * (y2 - y1)));
+                board_x + (board_x_spacing * x1) as i32 - line_w as i32,
+                board_y + (board_y_spacing * y1) as i32 - line_w as i32,
+                line_w + board_x_spacing * (x2 - x1),
+                line_w + board_y_spacing * (y2 - y1));
+
+        gfx.backend.fill_rect(colour, line);
+    }
+
+    // The whole board at one pixel per tile, with the part on screen
+    // outlined.
+    fn draw_minimap(gfx: &mut GfxLib, state: &GuiState, board: &Board, rect: Rect) {
+        let scale = state.screen_size.2;
+        let theme = state.theme;
+        // first pixel of tile i, across or down
+        let size = state.minimap_tile_size(board);
+        let px = |i: i32| (i as f32 * size) as i32;
+
+        let border = Rect::new(
+                rect.x() - scale as i32, rect.y() - scale as i32,
+                rect.width() + 2 * scale, rect.height() + 2 * scale);
+        gfx.backend.fill_rect(theme.foreground, border);
+        gfx.backend.fill_rect(theme.background, rect);
+
+        for y in 0..board.height {
+            for x in 0..board.width {
+                let colour = match board.get(x as u32, y as u32) {
+                    Some(Tile::Filled) => theme.foreground,
+                    Some(Tile::CrossedOut) => theme.grid,
+                    _ => continue
+                };
+
+                let (x, y) = (x as i32, y as i32);
+                gfx.backend.fill_rect(colour, Rect::new(
+                        rect.x() + px(x),
+                        rect.y() + px(y),
+                        max(1, px(x + 1) - px(x)) as u32,
+                        max(1, px(y + 1) - px(y)) as u32));
+            }
+        }
+
+        // tiles on screen, clipped to the board
+        let (screen_w, screen_h, _) = state.screen_size;
+        let x_spacing = state.scaled(TILE_WIDTH + 2) as i32;
+        let y_spacing = state.scaled(TILE_HEIGHT + 2) as i32;
+        let canvas_h = (screen_h - scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32;
+        let x1 = max(0, -state.offset_x / x_spacing);
+        let y1 = max(0, -state.offset_y / y_spacing);
+        let x2 = min(board.width as i32, (screen_w as i32 - state.offset_x) / x_spacing + 1);
+        let y2 = min(board.height as i32, (canvas_h - state.offset_y) / y_spacing + 1);
+
+        if x1 < x2 && y1 < y2 {
+            gfx.backend.draw_rect(theme.active, Rect::new(
+                    rect.x() + px(x1),
+                    rect.y() + px(y1),
+                    max(1, px(x2) - px(x1)) as u32,
+                    max(1, px(y2) - px(y1)) as u32));
+        }
+    }
+
+    // Length of the line being dragged, next to the mouse.
+    fn draw_drag_length(gfx: &mut GfxLib, state: &GuiState, length: u32) {
+        let scale = state.screen_size.2;
+        let theme = state.theme;
+
+        let w = scale * (text_pixel_width(length, 1) + 4);
+        let h = scale * (FONT_HEIGHT + 4);
+        let panel = Rect::new(
+                state.mouse_x + (scale * 8) as i32,
+                state.mouse_y - (scale * 8) as i32 - h as i32,
+                w, h);
+
+        gfx.backend.fill_rect(theme.highlight, panel);
+        gfx.backend.draw_rect(theme.foreground, panel);
+        gfx.text_right(Font::Unsolved, length, scale,
+                panel.x() + (w - scale * 2) as i32,
+                panel.y() + (scale * 2) as i32);
+    }
+
+    // Lines of text in a panel, centred above the toolbar.
+    fn draw_message(gfx: &mut GfxLib, state: &GuiState, message: &MessageOverlay) {
+        let (screen_w, screen_h, scale) = state.screen_size;
+        let theme = state.theme;
+        let max_chars = (screen_w / scale).saturating_sub(8) / text::GLYPH_ADVANCE;
+
+        let lines: Vec<String> = message.lines.iter()
+            .flat_map(|ln| text::wrap_text(ln, max_chars as usize)).collect();
+        let w = lines.iter().map(|ln| text::text_width(ln, 1)).max().unwrap_or(0) + 8;
+        let h = LINE_ADVANCE * lines.len() as u32 - (LINE_ADVANCE - GLYPH_HEIGHT) + 8;
+
+        let toolbar_h = scale * (TOOLBAR_BUTTON_HEIGHT + 6);
+        let panel = Rect::new(
+                (screen_w as i32 - (scale * w) as i32) / 2,
+                screen_h as i32 - (toolbar_h + scale * (h + 4)) as i32,
+                scale * w, scale * h);
+
+        gfx.backend.fill_rect(theme.highlight, panel);
+        gfx.backend.draw_rect(theme.foreground, panel);
+
+        for (i, ln) in lines.iter().enumerate() {
+            gfx.draw_text(ln, scale,
+                    panel.x() + (scale * 4) as i32,
+                    panel.y() + (scale * (4 + LINE_ADVANCE * i as u32)) as i32);
+        }
+    }
+
+    // A crossed out tile, followed by the line and column of the problem,
+    // with the message below.
+    fn draw_error(gfx: &mut GfxLib, state: &GuiState, error: &ErrorOverlay) {
+        let (screen_w, screen_h, scale) = state.screen_size;
+        let colour_shade = Color::RGBA(0x00, 0x00, 0x00, 0x80);
+        let theme = state.theme;
+
+        let max_chars = min(MAX_ERROR_CHARS,
+                (screen_w / scale).saturating_sub(16) / text::GLYPH_ADVANCE);
+        let lines = text::wrap_text(&error.message, max_chars as usize);
+
+        let mut w = 4 + TILE_WIDTH + 4;
+        if let Some((line_num, column)) = error.position {
+            w = w + text_pixel_width(line_num as u32, 1) + 8
+                + text_pixel_width(column as u32, 1) + 4;
+        }
+        for ln in lines.iter() {
+            w = max(w, 4 + text::text_width(ln, 1) + 4);
+        }
+        let h = 4 + TILE_HEIGHT + 4 + LINE_ADVANCE * lines.len() as u32;
+
+        let panel = Rect::new(
+                (screen_w as i32 - (scale * w) as i32) / 2,
+                (screen_h as i32 - (scale * h) as i32) / 2,
+                scale * w, scale * h);
+        let x = panel.x() + (scale * 4) as i32;
+        let y = panel.y() + (scale * 4) as i32;
+
+        gfx.backend.fill_rect(colour_shade, Rect::new(0, 0, screen_w, screen_h));
+        gfx.backend.fill_rect(theme.highlight, panel);
+        gfx.backend.draw_rect(theme.foreground, panel);
+        gfx.draw(Res::TileCrossedOut,
+                Rect::new(x, y, scale * TILE_WIDTH, scale * TILE_HEIGHT));
+
+        if let Some((line_num, column)) = error.position {
+            let text_y = y + (scale * (TILE_HEIGHT - FONT_HEIGHT) / 2) as i32;
+            let line_right = x + (scale * (TILE_WIDTH + 4
+                        + text_pixel_width(line_num as u32, 1))) as i32;
+            let column_right = line_right + (scale * (8
+                        + text_pixel_width(column as u32, 1))) as i32;
+
+            gfx.text_right(Font::Conflict, line_num as u32, scale, line_right, text_y);
+            gfx.text_right(Font::Conflict, column as u32, scale, column_right, text_y);
+        }
 
-        let _ = gfx.renderer.fill_rect(line);
+        for (i, ln) in lines.iter().enumerate() {
+            gfx.draw_text(ln, scale, x,
+                    y + (scale * (TILE_HEIGHT + 4 + LINE_ADVANCE * i as u32)) as i32);
+        }
     }
 
     fn draw_widget(gfx: &mut GfxLib, state: &GuiState, widget: &Widget) {
@@ -539,11 +1128,49 @@ impl<'a> Gui<'a> {
                     active
                 } else {
                     inactive
-                }
+                },
+
+            WidgetType::Tool(tool) => {
+                Gui::draw_tool_widget(gfx, state, tool, widget.rect);
+                return
+            }
         };
 
         gfx.draw(res, widget.rect);
     }
+
+    // There are no sprites for the tools, so draw them from rectangles.
+    fn draw_tool_widget(gfx: &mut GfxLib, state: &GuiState, tool: Tool, rect: Rect) {
+        let scale = state.screen_size.2;
+        let theme = state.theme;
+
+        // the fill and icon colours of the sprite buttons
+        gfx.backend.fill_rect(theme.sprites[2], rect);
+        for i in 0..scale {
+            let colour = if tool == state.tool { theme.active } else { theme.sprites[1] };
+            gfx.backend.draw_rect(colour, Rect::new(
+                    rect.x() + i as i32, rect.y() + i as i32,
+                    rect.width() - 2 * i, rect.height() - 2 * i));
+        }
+
+        // icon, in toolbar pixels from the top-left of the button
+        let pixel = |x: u32, y: u32, w: u32, h: u32|
+            Rect::new(rect.x() + (scale * x) as i32, rect.y() + (scale * y) as i32,
+                    scale * w, scale * h);
+
+        match tool {
+            Tool::Brush =>
+                gfx.backend.fill_rect(theme.sprites[1], pixel(5, 3, 3, 3)),
+
+            Tool::Line =>
+                for i in 0..3 {
+                    gfx.backend.fill_rect(theme.sprites[1], pixel(3 + 2 * i, 5 - i, 3, 1));
+                },
+
+            Tool::Rectangle =>
+                gfx.backend.draw_rect(theme.sprites[1], pixel(3, 2, 7, 5))
+        }
+    }
 }
 
 impl GuiState {
@@ -554,10 +1181,12 @@ impl GuiState {
         GuiState {
             mode: GuiMode::Neutral,
             selected_paint: Tile::Filled,
+            tool: Tool::Brush,
             board: None,
+            drag_origin: None,
             new_changes: false,
             screen_size: screen_size,
-            board_scale: 1,
+            board_scale: 1.0,
             offset_x: 0,
             offset_y: 0,
             board_pixel_width: 0,
@@ -566,7 +1195,22 @@ impl GuiState {
             col_rule_max_pixel_height: 0,
             last_mouse_x: 0,
             last_mouse_y: 0,
-            highlight: None
+            mouse_x: 0,
+            mouse_y: 0,
+            free_paint: false,
+            drag_start: None,
+            drag_last: None,
+            drag_axis: None,
+            highlight: None,
+            cursor: None,
+            hover_clue: None,
+            show_minimap: false,
+            theme: &THEMES[0],
+            clue_markers: false,
+            touch: None,
+            fingers: 0,
+            gesture_centre: None,
+            pinch: 0.0
         }
     }
 
@@ -580,13 +1224,13 @@ impl GuiState {
 
     fn calc_default_offset(&self) -> (i32, i32) {
         let (screen_w, screen_h, toolbar_scale) = self.screen_size;
-        let board_scale = self.board_scale as i32;
-        let text_scale = min(2, board_scale);
+        let text_scale = self.text_scale() as i32;
         let toolbar_h = toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6);
         let canvas_w = screen_w as i32;
         let canvas_h = (screen_h - toolbar_h) as i32;
-        let scaled_board_w = board_scale * (self.board_pixel_width + 2) as i32;
-        let scaled_text_w = board_scale * 4 + text_scale * self.row_rule_max_pixel_width as i32;
+        let scaled_board_w = self.scaled(self.board_pixel_width + 2) as i32;
+        let scaled_text_w = self.scaled(4) as i32
+            + text_scale * self.row_rule_max_pixel_width as i32;
 
         let offset_x =
             if canvas_w < scaled_board_w {
@@ -603,8 +1247,8 @@ impl GuiState {
                 (canvas_w - scaled_board_w) / 2
             };
         let offset_y = (canvas_h
-                        - board_scale * self.board_pixel_height as i32
-                        + board_scale * 4
+                        - self.scaled(self.board_pixel_height) as i32
+                        + self.scaled(4) as i32
                         + text_scale * self.col_rule_max_pixel_height as i32) / 2;
 
         (offset_x, offset_y)
@@ -612,18 +1256,19 @@ impl GuiState {
 
     fn on_new_puzzle(&mut self, puzzle: &Puzzle) {
         let b = puzzle.get_board();
-        let (col_rules, row_rules) = puzzle.get_rules();
         let board_x_spacing = TILE_WIDTH + 2;
         let board_y_spacing = TILE_HEIGHT + 2;
 
+        self.hover_clue = None;
+
+        // keep the cursor if it is still on the board
+        self.cursor = self.cursor.filter(|&(x, y)|
+                x < b.width as u32 && y < b.height as u32);
+
         self.board_pixel_width = board_x_spacing * b.width as u32 - 2;
         self.board_pixel_height = board_y_spacing * b.height as u32 - 2;
 
-        self.row_rule_max_pixel_width = row_rules.iter().fold(0,
-                |n, r| max(n, calc_rule_width(r)));
-
-        self.col_rule_max_pixel_height = col_rules.iter().fold(0,
-                |n, r| max(n, calc_rule_height(r)));
+        self.calc_rule_pixel_sizes(puzzle);
 
         let (mut offset_x, mut offset_y) = self.calc_default_offset();
 
@@ -645,6 +1290,115 @@ impl GuiState {
         self.offset_y = offset_y;
     }
 
+    // Where each clue is drawn, as (clue, value, rect).
+    // Clues stay on screen when the board is panned past them, so they
+    // are placed from no further left or up than the clue edges.
+    fn clue_rects(&self, rules: Rules) -> Vec<(Clue, u32, Rect)> {
+        let text_scale = self.text_scale();
+        let tile_w = self.scaled(TILE_WIDTH + 2) - self.scaled(2);
+        let tile_h = self.scaled(TILE_HEIGHT + 2) - self.scaled(2);
+        let (col_rules, row_rules) = rules;
+        let x_spacing = (text_scale * 5) as i32;
+        let y_spacing = (text_scale * (FONT_HEIGHT + 2)) as i32;
+        let (edge_x, edge_y) = self.clue_edges();
+        let clue_x = max(self.offset_x, edge_x);
+        let clue_y = max(self.offset_y, edge_y);
+        let mut rects = Vec::new();
+
+        // columns, from the bottom up, centred on the column
+        let mut x = self.offset_x + (tile_w / 2 + 1) as i32;
+        for (col, rule) in col_rules.iter().enumerate() {
+            let mut y = clue_y - (self.scaled(4) + text_scale * FONT_HEIGHT) as i32;
+
+            // hidden under the pinned row clues
+            if x < edge_x && self.offset_x < edge_x {
+                x = x + self.scaled(TILE_WIDTH + 2) as i32;
+                continue;
+            }
+
+            for i in (0..rule.len()).rev() {
+                let v = rule[i];
+                let w = text_pixel_width(v, text_scale);
+                let right = x + w as i32 / 2;
+
+                rects.push((Clue::Column(col, i), v,
+                        Rect::new(right - w as i32, y, w, text_scale * FONT_HEIGHT)));
+                y = y - y_spacing;
+            }
+
+            x = x + self.scaled(TILE_WIDTH + 2) as i32;
+        }
+
+        // rows, from the right
+        let mut y = self.offset_y + (tile_h as i32 - (text_scale * FONT_HEIGHT) as i32) / 2;
+        for (row, rule) in row_rules.iter().enumerate() {
+            let mut x = clue_x - self.scaled(4) as i32;
+
+            // hidden under the pinned column clues
+            if y < edge_y && self.offset_y < edge_y {
+                y = y + self.scaled(TILE_HEIGHT + 2) as i32;
+                continue;
+            }
+
+            for i in (0..rule.len()).rev() {
+                let v = rule[i];
+                let w = text_pixel_width(v, text_scale);
+
+                rects.push((Clue::Row(row, i), v,
+                        Rect::new(x - w as i32, y, w, text_scale * FONT_HEIGHT)));
+                x = x - x_spacing - w as i32;
+            }
+
+            y = y + self.scaled(TILE_HEIGHT + 2) as i32;
+        }
+
+        rects
+    }
+
+    // Width of the row clues and height of the column clues on screen.
+    fn clue_edges(&self) -> (i32, i32) {
+        let text_scale = self.text_scale();
+        let edge_x = self.scaled(4) + text_scale * (self.row_rule_max_pixel_width + 2);
+        let edge_y = self.scaled(4) + text_scale * (self.col_rule_max_pixel_height + 2);
+        (edge_x as i32, edge_y as i32)
+    }
+
+    // True if (x, y) is on clues pinned over the board, hiding the tiles.
+    fn on_pinned_clues(&self, x: i32, y: i32) -> bool {
+        let (edge_x, edge_y) = self.clue_edges();
+        (self.offset_x < edge_x && x < edge_x) || (self.offset_y < edge_y && y < edge_y)
+    }
+
+    // Size in pixels of v board pixels at the current zoom.
+    fn scaled(&self, v: u32) -> u32 {
+        scale_pixels(self.board_scale, v)
+    }
+
+    // The font is only drawn at whole scales.
+    fn text_scale(&self) -> u32 {
+        min(2, self.board_scale as u32)
+    }
+
+    fn find_clue(&self, rules: Rules, mx: i32, my: i32) -> Option<Clue> {
+        let text_scale = self.text_scale() as i32;
+
+        self.clue_rects(rules).iter()
+            .find(|&&(_, _, r)|
+                r.x() - text_scale <= mx && mx < r.x() + r.width() as i32 + text_scale
+                && r.y() - text_scale <= my && my < r.y() + r.height() as i32 + text_scale)
+            .map(|&(clue, _, _)| clue)
+    }
+
+    fn calc_rule_pixel_sizes(&mut self, puzzle: &Puzzle) {
+        let (col_rules, row_rules) = puzzle.get_rules();
+
+        self.row_rule_max_pixel_width = row_rules.iter().fold(0,
+                |n, r| max(n, calc_rule_width(r)));
+
+        self.col_rule_max_pixel_height = col_rules.iter().fold(0,
+                |n, r| max(n, calc_rule_height(r)));
+    }
+
     fn on_resize_window(&mut self, new_screen_w: u32, new_screen_h: u32) {
         let (old_screen_w, old_screen_h, _) = self.screen_size;
         let old_offset_x = self.offset_x;
@@ -663,57 +1417,399 @@ impl GuiState {
                             min(desired_y, old_offset_y + diff_y));
     }
 
-    fn on_key_down(&mut self, keycode: Keycode) -> PicrossAction {
+    // Shift and ctrl are not commands, so cannot be rebound.
+    fn on_key_down(&mut self, board: &Board, keycode: Keycode,
+            command: Option<Command>, keymod: Mod) -> PicrossAction {
+        let shift = keymod.intersects(LSHIFTMOD | RSHIFTMOD);
+
+        match keycode {
+            Keycode::LCtrl | Keycode::RCtrl => self.free_paint = true,
+            _ => {}
+        }
+
+        match command {
+            Some(c) => self.on_command(board, c, shift),
+            None => PicrossAction::NoOp
+        }
+    }
+
+    fn on_command(&mut self, board: &Board, command: Command, shift: bool)
+            -> PicrossAction {
+        let cursor_move = match command {
+            Command::CursorLeft => Some((-1, 0)),
+            Command::CursorRight => Some((1, 0)),
+            Command::CursorUp => Some((0, -1)),
+            Command::CursorDown => Some((0, 1)),
+            _ => None
+        };
+
+        if let Some((dx, dy)) = cursor_move {
+            if self.mode == GuiMode::Neutral && shift {
+                // start a run, painting the tile under the cursor too
+                self.mode = GuiMode::KeyPaint;
+                self.board = Some(board.clone());
+                self.new_changes = false;
+                self.move_cursor(board, 0, 0);
+            }
+
+            if self.mode == GuiMode::Neutral || self.mode == GuiMode::KeyPaint {
+                self.move_cursor(board, dx, dy);
+            }
+            return PicrossAction::NoOp
+        }
+
         if self.mode != GuiMode::Neutral {
             return PicrossAction::NoOp
         }
 
+        match command {
+            Command::Fill => return self.set_cursor_tile(board, Tile::Filled),
+            Command::CrossOut => return self.set_cursor_tile(board, Tile::CrossedOut),
+            Command::Clear => return self.set_cursor_tile(board, Tile::Empty),
+
+            Command::Undo => return PicrossAction::Undo,
+            Command::Redo => return PicrossAction::Redo,
+            Command::AutoFill => return PicrossAction::AutoFill,
+            Command::ToggleAutoCross => return PicrossAction::ToggleAutoCross,
+            Command::Random => return PicrossAction::Random,
+            Command::Daily => return PicrossAction::Daily,
+
+            Command::ToggleEditor => return PicrossAction::ToggleEditor,
+            Command::EditorNarrower => return PicrossAction::ResizeEditor(-1, 0),
+            Command::EditorWider => return PicrossAction::ResizeEditor(1, 0),
+            Command::EditorShorter => return PicrossAction::ResizeEditor(0, -1),
+            Command::EditorTaller => return PicrossAction::ResizeEditor(0, 1),
+            Command::CheckUnique => return PicrossAction::CheckUnique,
+            Command::Save => return PicrossAction::Save,
+
+            Command::PaintEmpty => self.selected_paint = Tile::Empty,
+            Command::PaintCrossedOut => self.selected_paint = Tile::CrossedOut,
+            Command::PaintFilled => self.selected_paint = Tile::Filled,
+
+            Command::NextTool =>
+                self.tool = match self.tool {
+                    Tool::Brush => Tool::Line,
+                    Tool::Line => Tool::Rectangle,
+                    Tool::Rectangle => Tool::Brush
+                },
+
+            Command::ZoomToFit => self.fit_to_window(),
+            Command::ToggleMinimap => self.show_minimap = !self.show_minimap,
+            Command::ToggleClueMarkers => self.clue_markers = !self.clue_markers,
+
+            // done by the Gui, which has the window and sprites
+            Command::ToggleFullscreen | Command::NextTheme => {},
+
+            Command::CursorLeft | Command::CursorRight
+                | Command::CursorUp | Command::CursorDown => {}
+        }
+        PicrossAction::NoOp
+    }
+
+    // Releasing shift ends a run, as one step of the history.
+    fn on_key_up(&mut self, keycode: Keycode) -> PicrossAction {
+        match keycode {
+            Keycode::LCtrl | Keycode::RCtrl => self.free_paint = false,
+            _ => {}
+        }
+
+        if self.mode != GuiMode::KeyPaint {
+            return PicrossAction::NoOp
+        }
+
         match keycode {
-            Keycode::Z => return PicrossAction::Undo,
-            Keycode::X => return PicrossAction::Redo,
-            Keycode::A => return PicrossAction::AutoFill,
+            Keycode::LShift | Keycode::RShift => self.end_key_paint(),
+            _ => PicrossAction::NoOp
+        }
+    }
+
+    // Key releases are not seen while the window is in the background, so
+    // losing focus lets go of shift and ctrl.
+    fn on_focus_lost(&mut self) -> PicrossAction {
+        self.free_paint = false;
+
+        if self.mode == GuiMode::KeyPaint {
+            self.end_key_paint()
+        } else {
+            PicrossAction::NoOp
+        }
+    }
+
+    fn end_key_paint(&mut self) -> PicrossAction {
+        self.mode = GuiMode::Neutral;
+
+        if self.board.is_some() && self.new_changes {
+            return PicrossAction::Update(self.board.take().unwrap())
+        } else {
+            self.board = None;
+        }
+
+        PicrossAction::NoOp
+    }
+
+    // Controller buttons work like the cursor keys.  Holding a face
+    // button paints its tile while the d-pad moves.
+    fn on_pad_button_down(&mut self, board: &Board, button: Button) -> PicrossAction {
+        let cursor_move = match button {
+            Button::DPadLeft => Some((-1, 0)),
+            Button::DPadRight => Some((1, 0)),
+            Button::DPadUp => Some((0, -1)),
+            Button::DPadDown => Some((0, 1)),
+            _ => None
+        };
+
+        if let Some((dx, dy)) = cursor_move {
+            match self.mode {
+                GuiMode::Neutral | GuiMode::PadPaint(_) => self.move_cursor(board, dx, dy),
+                _ => {}
+            }
+            return PicrossAction::NoOp
+        }
+
+        if self.mode != GuiMode::Neutral {
+            return PicrossAction::NoOp
+        }
+
+        let paint = match button {
+            Button::A => Tile::Filled,
+            Button::B => Tile::CrossedOut,
+            Button::X => Tile::Empty,
+
+            Button::Y => return PicrossAction::AutoFill,
+            Button::LeftShoulder => return PicrossAction::Undo,
+            Button::RightShoulder => return PicrossAction::Redo,
+            _ => return PicrossAction::NoOp
+        };
+
+        // start a run, painting the tile under the cursor too
+        self.mode = GuiMode::PadPaint(paint);
+        self.board = Some(board.clone());
+        self.new_changes = false;
+        self.move_cursor(board, 0, 0);
+
+        PicrossAction::NoOp
+    }
+
+    // Releasing the button ends a run, as one step of the history.
+    fn on_pad_button_up(&mut self, button: Button) -> PicrossAction {
+        let paint = match button {
+            Button::A => Tile::Filled,
+            Button::B => Tile::CrossedOut,
+            Button::X => Tile::Empty,
+            _ => return PicrossAction::NoOp
+        };
+
+        if self.mode != GuiMode::PadPaint(paint) {
+            return PicrossAction::NoOp
+        }
+
+        self.mode = GuiMode::Neutral;
 
-            Keycode::Num1 => self.selected_paint = Tile::Empty,
-            Keycode::Num2 => self.selected_paint = Tile::CrossedOut,
-            Keycode::Num3 => self.selected_paint = Tile::Filled,
+        if self.board.is_some() && self.new_changes {
+            return PicrossAction::Update(self.board.take().unwrap())
+        } else {
+            self.board = None;
+        }
 
+        PicrossAction::NoOp
+    }
+
+    fn move_cursor(&mut self, board: &Board, dx: i32, dy: i32) {
+        let (tx, ty) = match self.cursor {
+            Some((x, y)) => (
+                max(0, min(x as i32 + dx, board.width as i32 - 1)) as u32,
+                max(0, min(y as i32 + dy, board.height as i32 - 1)) as u32),
+            None => (0, 0)
+        };
+
+        self.cursor = Some((tx, ty));
+        self.highlight = self.cursor;
+        self.scroll_to_tile(tx, ty);
+
+        match self.mode {
+            GuiMode::KeyPaint => {
+                let paint = self.selected_paint;
+                self.paint_tile(tx, ty, paint);
+            },
+            GuiMode::PadPaint(paint) => self.paint_tile(tx, ty, paint),
             _ => {}
         }
+    }
+
+    fn set_cursor_tile(&mut self, board: &Board, tile: Tile) -> PicrossAction {
+        if let Some((tx, ty)) = self.cursor {
+            if board.get(tx, ty) != Some(tile) {
+                let mut b = board.clone();
+                b.set(tx, ty, tile);
+                return PicrossAction::Update(b)
+            }
+        }
+
         PicrossAction::NoOp
     }
 
+    // Pan so that the tile is on the screen.
+    fn scroll_to_tile(&mut self, tx: u32, ty: u32) {
+        let (screen_w, screen_h, toolbar_scale) = self.screen_size;
+        let canvas_h = (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32;
+        let x_spacing = self.scaled(TILE_WIDTH + 2) as i32;
+        let y_spacing = self.scaled(TILE_HEIGHT + 2) as i32;
+        let x = self.offset_x + x_spacing * tx as i32;
+        let y = self.offset_y + y_spacing * ty as i32;
+        let (edge_x, edge_y) = self.clue_edges();
+
+        // keep the tile out from under the pinned clues
+        if x < edge_x {
+            self.offset_x = self.offset_x - x + edge_x;
+        } else if x + x_spacing > screen_w as i32 {
+            self.offset_x = self.offset_x - (x + x_spacing - screen_w as i32);
+        }
+
+        if y < edge_y {
+            self.offset_y = self.offset_y - y + edge_y;
+        } else if y + y_spacing > canvas_h {
+            self.offset_y = self.offset_y - (y + y_spacing - canvas_h);
+        }
+    }
+
+    // Project the tile onto the row or column being dragged along.
+    fn lock_to_line(&mut self, tx: u32, ty: u32) -> (u32, u32) {
+        let (sx, sy) = match self.drag_start {
+            Some(start) => start,
+            None => {
+                self.drag_start = Some((tx, ty));
+                return (tx, ty)
+            }
+        };
+
+        if self.free_paint || ((tx, ty) == (sx, sy) && self.drag_axis.is_none()) {
+            return (tx, ty)
+        }
+
+        if self.drag_axis.is_none() {
+            let dx = (tx as i32 - sx as i32).abs();
+            let dy = (ty as i32 - sy as i32).abs();
+            self.drag_axis = Some(if dx >= dy { Axis::Horizontal } else { Axis::Vertical });
+        }
+
+        match self.drag_axis {
+            Some(Axis::Horizontal) => (tx, sy),
+            _ => (sx, ty)
+        }
+    }
+
+    // Redraw the line or rectangle from the start of the drag to the tile,
+    // on top of the board from before the drag.
+    fn draw_shape(&mut self, tx: u32, ty: u32) {
+        let (sx, sy) = match self.drag_start {
+            Some(start) => start,
+            None => {
+                self.drag_start = Some((tx, ty));
+                (tx, ty)
+            }
+        };
+
+        self.board = self.drag_origin.clone();
+        self.new_changes = false;
+        self.drag_last = Some((tx, ty));
+
+        let tiles =
+            if self.tool == Tool::Line {
+                line_tiles(sx, sy, tx, ty)
+            } else {
+                let mut tiles = Vec::new();
+                for y in min(sy, ty)..max(sy, ty) + 1 {
+                    for x in min(sx, tx)..max(sx, tx) + 1 {
+                        tiles.push((x, y));
+                    }
+                }
+                tiles
+            };
+
+        for (x, y) in tiles {
+            self.drag_paint_tile(x, y);
+        }
+    }
+
+    // lmb will only draw on empty tiles, rmb will clear any tile.
+    fn drag_paint_tile(&mut self, tx: u32, ty: u32) {
+        if self.mode == GuiMode::HoldLMB {
+            let paint = self.selected_paint;
+            self.paint_tile(tx, ty, paint);
+        } else if let Some(ref mut b) = self.board {
+            if b.get(tx, ty) != Some(Tile::Empty) {
+                b.set(tx, ty, Tile::Empty);
+                self.new_changes = true;
+            }
+        }
+    }
+
+    // Number of tiles in the line being dragged, if locked to a line.
+    fn drag_length(&self) -> Option<u32> {
+        if self.mode != GuiMode::HoldLMB && self.mode != GuiMode::HoldRMB {
+            return None
+        }
+        if self.tool == Tool::Rectangle || (self.tool == Tool::Brush && self.free_paint) {
+            return None
+        }
+
+        match (self.drag_start, self.drag_last) {
+            (Some((sx, sy)), Some((lx, ly))) => {
+                let dx = (lx as i32 - sx as i32).abs();
+                let dy = (ly as i32 - sy as i32).abs();
+                Some(max(dx, dy) as u32 + 1)
+            },
+            _ => None
+        }
+    }
+
+    // Paint, which only draws on empty tiles.
+    fn paint_tile(&mut self, tx: u32, ty: u32, new_tile: Tile) {
+        if let Some(ref mut b) = self.board {
+            let old_tile = b.get(tx, ty).unwrap();
+
+            if (old_tile == Tile::Empty && new_tile != Tile::Empty)
+                || (old_tile != Tile::Empty && new_tile == Tile::Empty) {
+                b.set(tx, ty, new_tile);
+                self.new_changes = true;
+            }
+        }
+    }
+
     fn on_mouse_motion(&mut self, board: &Board, mx: i32, my: i32) -> PicrossAction {
         let maybe_tile_coord = convert_mouse_coord_to_tile_coord(
                 board, self.board_scale, mx - self.offset_x, my - self.offset_y);
         self.highlight = maybe_tile_coord;
-
-        if self.mode == GuiMode::HoldLMB {
-            // lmb will only draw on empty tiles.
-            if let Some(ref mut b) = self.board {
-                if let Some((tx, ty)) = maybe_tile_coord {
-                    let old_tile = b.get(tx, ty).unwrap();
-                    let new_tile = self.selected_paint;
-
-                    if (old_tile == Tile::Empty && new_tile != Tile::Empty)
-                        || (old_tile != Tile::Empty && new_tile == Tile::Empty) {
-                        b.set(tx, ty, new_tile);
-                        self.new_changes = true;
-                    }
-                }
+        self.mouse_x = mx;
+        self.mouse_y = my;
+
+        if (self.mode == GuiMode::HoldLMB || self.mode == GuiMode::HoldRMB)
+                && self.tool != Tool::Brush {
+            if let Some((tx, ty)) = maybe_tile_coord {
+                self.draw_shape(tx, ty);
+                self.highlight = self.drag_last;
             }
-        } else if self.mode == GuiMode::HoldRMB {
-            // rmb will clear any tile.
-            if let Some(ref mut b) = self.board {
-                if let Some((tx, ty)) = maybe_tile_coord {
-                    let old_tile = b.get(tx, ty).unwrap();
-                    let new_tile = Tile::Empty;
+        } else if self.mode == GuiMode::HoldLMB || self.mode == GuiMode::HoldRMB {
+            if let Some((tx, ty)) = maybe_tile_coord {
+                let (tx, ty) = self.lock_to_line(tx, ty);
+
+                // fill in tiles skipped by fast movement along the line
+                let (lx, ly) = match self.drag_last {
+                    Some(last) if !self.free_paint => last,
+                    _ => (tx, ty)
+                };
 
-                    if old_tile != Tile::Empty {
-                        b.set(tx, ty, new_tile);
-                        self.new_changes = true;
+                for y in min(ly, ty)..max(ly, ty) + 1 {
+                    for x in min(lx, tx)..max(lx, tx) + 1 {
+                        self.drag_paint_tile(x, y);
                     }
                 }
+
+                self.drag_last = Some((tx, ty));
+                self.highlight = self.drag_last;
             }
+        } else if self.mode == GuiMode::MinimapPan {
+            self.centre_on_minimap(board, mx, my);
         } else if self.mode == GuiMode::Pan {
             self.offset_x = self.offset_x + mx - self.last_mouse_x;
             self.offset_y = self.offset_y + my - self.last_mouse_y;
@@ -738,10 +1834,17 @@ impl GuiState {
                 WidgetType::Redo => return PicrossAction::Redo,
 
                 WidgetType::Paint(paint,_,_) =>
-                    self.selected_paint = paint
+                    self.selected_paint = paint,
+
+                WidgetType::Tool(tool) =>
+                    self.tool = tool
             }
         } else {
             self.mode = GuiMode::HoldLMB;
+            self.drag_origin = Some(board.clone());
+            self.drag_start = None;
+            self.drag_last = None;
+            self.drag_axis = None;
 
             if self.board.is_none() {
                 self.board = Some(board.clone());
@@ -754,11 +1857,15 @@ impl GuiState {
     }
 
     fn on_lmb_up(&mut self) -> PicrossAction {
+        if self.mode == GuiMode::MinimapPan {
+            self.mode = GuiMode::Neutral;
+        }
         if self.mode != GuiMode::HoldLMB {
             return PicrossAction::NoOp
         }
 
         self.mode = GuiMode::Neutral;
+        self.drag_origin = None;
 
         if self.board.is_some() && self.new_changes {
             return PicrossAction::Update(self.board.take().unwrap())
@@ -775,6 +1882,10 @@ impl GuiState {
         }
 
         self.mode = GuiMode::HoldRMB;
+        self.drag_origin = Some(board.clone());
+        self.drag_start = None;
+        self.drag_last = None;
+        self.drag_axis = None;
 
         if self.board.is_none() {
             self.board = Some(board.clone());
@@ -786,11 +1897,15 @@ impl GuiState {
     }
 
     fn on_rmb_up(&mut self) -> PicrossAction {
+        if self.mode == GuiMode::MinimapPan {
+            self.mode = GuiMode::Neutral;
+        }
         if self.mode != GuiMode::HoldRMB {
             return PicrossAction::NoOp
         }
 
         self.mode = GuiMode::Neutral;
+        self.drag_origin = None;
 
         if self.board.is_some() && self.new_changes {
             return PicrossAction::Update(self.board.take().unwrap())
@@ -818,17 +1933,213 @@ impl GuiState {
         PicrossAction::NoOp
     }
 
+    // Zoom about the mouse, keeping the same spot of the board under it.
     fn on_wheel(&mut self, y: i32) -> PicrossAction {
-        self.board_scale = max(1, min(self.board_scale as i32 + y, 5)) as u32;
+        let new_scale = (self.board_scale + ZOOM_STEP * y as f32)
+            .max(MIN_ZOOM).min(MAX_ZOOM);
+        let old_x_spacing = self.scaled(TILE_WIDTH + 2) as f32;
+        let old_y_spacing = self.scaled(TILE_HEIGHT + 2) as f32;
+        let tx = (self.mouse_x - self.offset_x) as f32 / old_x_spacing;
+        let ty = (self.mouse_y - self.offset_y) as f32 / old_y_spacing;
+
+        self.board_scale = new_scale;
+
+        let new_x_spacing = self.scaled(TILE_WIDTH + 2) as f32;
+        let new_y_spacing = self.scaled(TILE_HEIGHT + 2) as f32;
+        self.offset_x = self.mouse_x - (tx * new_x_spacing).round() as i32;
+        self.offset_y = self.mouse_y - (ty * new_y_spacing).round() as i32;
+
+        PicrossAction::NoOp
+    }
+
+    // Finger positions are from 0 to 1 across the window.
+    fn finger_coord(&self, x: f32, y: f32) -> (i32, i32) {
+        let (screen_w, screen_h, _) = self.screen_size;
+        ((x * screen_w as f32) as i32, (y * screen_h as f32) as i32)
+    }
+
+    fn on_finger_down(&mut self, finger_id: i64, x: i32, y: i32, ticks: u32) {
+        self.fingers = self.fingers + 1;
+
+        if self.fingers == 1 && self.mode == GuiMode::Neutral {
+            self.touch = Some(Touch { finger_id: finger_id, x: x, y: y, ticks: ticks });
+        } else {
+            // the start of a gesture
+            self.touch = None;
+        }
+    }
+
+    fn on_finger_motion(&mut self, finger_id: i64, x: i32, y: i32) {
+        if let Some(t) = self.touch {
+            if t.finger_id == finger_id
+                    && ((x - t.x).abs() > TAP_SLOP || (y - t.y).abs() > TAP_SLOP) {
+                self.touch = None;
+            }
+        }
+    }
+
+    // Some(x,y) if the finger was a tap.
+    fn on_finger_up(&mut self, finger_id: i64) -> Option<(i32, i32)> {
+        self.fingers = self.fingers.saturating_sub(1);
+        if self.fingers < 2 {
+            self.gesture_centre = None;
+            self.pinch = 0.0;
+        }
+
+        match self.touch.take() {
+            Some(t) if t.finger_id == finger_id => Some((t.x, t.y)),
+            _ => None
+        }
+    }
+
+    // Holding a finger on a tile crosses it out, or clears a cross.
+    fn on_long_press(&mut self, board: &Board, ticks: u32) -> Option<PicrossAction> {
+        let t = match self.touch {
+            Some(t) if ticks >= t.ticks + LONG_PRESS_TICKS => t,
+            _ => return None
+        };
+
+        self.touch = None;
+
+        let maybe_tile_coord = convert_mouse_coord_to_tile_coord(
+                board, self.board_scale, t.x - self.offset_x, t.y - self.offset_y);
+        if let Some((tx, ty)) = maybe_tile_coord {
+            let new_tile =
+                if board.get(tx, ty) == Some(Tile::CrossedOut) {
+                    Tile::Empty
+                } else {
+                    Tile::CrossedOut
+                };
+
+            let mut b = board.clone();
+            b.set(tx, ty, new_tile);
+            return Some(PicrossAction::Update(b))
+        }
+
+        Some(PicrossAction::NoOp)
+    }
+
+    // Two fingers move the board, and pinching zooms about them.
+    fn on_gesture(&mut self, num_fingers: u16, d_dist: f32, x: i32, y: i32)
+            -> PicrossAction {
+        if num_fingers < 2 || self.mode != GuiMode::Neutral {
+            return PicrossAction::NoOp
+        }
+
+        if let Some((last_x, last_y)) = self.gesture_centre {
+            self.offset_x = self.offset_x + x - last_x;
+            self.offset_y = self.offset_y + y - last_y;
+        }
+        self.gesture_centre = Some((x, y));
+
+        self.mouse_x = x;
+        self.mouse_y = y;
+        self.pinch = self.pinch + d_dist;
+
+        while self.pinch >= PINCH_STEP {
+            self.on_wheel(1);
+            self.pinch = self.pinch - PINCH_STEP;
+        }
+        while self.pinch <= -PINCH_STEP {
+            self.on_wheel(-1);
+            self.pinch = self.pinch + PINCH_STEP;
+        }
+
+        PicrossAction::NoOp
+    }
+
+    // Bottom right of the screen, above the toolbar.
+    fn minimap_rect(&self, board: &Board) -> Option<Rect> {
+        if !self.show_minimap {
+            return None
+        }
+
+        let (screen_w, screen_h, toolbar_scale) = self.screen_size;
+        let canvas_h = screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6);
+        let margin = toolbar_scale * 4;
+        let size = self.minimap_tile_size(board);
+        let w = max(1, (size * board.width as f32).ceil() as u32);
+        let h = max(1, (size * board.height as f32).ceil() as u32);
+
+        Some(Rect::new(
+                screen_w as i32 - (w + margin) as i32,
+                canvas_h as i32 - (h + margin) as i32,
+                w, h))
+    }
+
+    // Pixels per tile in the minimap: the toolbar scale, or less on large
+    // boards so that the minimap covers at most half the canvas each way.
+    fn minimap_tile_size(&self, board: &Board) -> f32 {
+        let (screen_w, screen_h, toolbar_scale) = self.screen_size;
+        let canvas_h = screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6);
+
+        (toolbar_scale as f32)
+            .min(screen_w as f32 / 2.0 / board.width as f32)
+            .min(canvas_h as f32 / 2.0 / board.height as f32)
+    }
+
+    fn in_minimap(&self, board: &Board, mx: i32, my: i32) -> bool {
+        match self.minimap_rect(board) {
+            Some(r) =>
+                r.x() <= mx && mx < r.x() + r.width() as i32
+                && r.y() <= my && my < r.y() + r.height() as i32,
+            None => false
+        }
+    }
+
+    fn on_minimap_press(&mut self, board: &Board, mx: i32, my: i32) -> PicrossAction {
+        self.mode = GuiMode::MinimapPan;
+        self.centre_on_minimap(board, mx, my);
         PicrossAction::NoOp
     }
+
+    // Move the view so the tile under the mouse is in the middle.
+    fn centre_on_minimap(&mut self, board: &Board, mx: i32, my: i32) {
+        let r = match self.minimap_rect(board) {
+            Some(r) => r,
+            None => return
+        };
+
+        let (screen_w, screen_h, toolbar_scale) = self.screen_size;
+        let canvas_h = (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32;
+        let x_spacing = self.scaled(TILE_WIDTH + 2) as i32;
+        let y_spacing = self.scaled(TILE_HEIGHT + 2) as i32;
+        let size = self.minimap_tile_size(board);
+        let tx = max(0, min(board.width as i32 - 1, ((mx - r.x()) as f32 / size) as i32));
+        let ty = max(0, min(board.height as i32 - 1, ((my - r.y()) as f32 / size) as i32));
+
+        self.offset_x = screen_w as i32 / 2 - x_spacing * tx - x_spacing / 2;
+        self.offset_y = canvas_h / 2 - y_spacing * ty - y_spacing / 2;
+    }
+
+    // The largest zoom showing the board and all the clues, centred.
+    fn fit_to_window(&mut self) {
+        let (screen_w, screen_h, toolbar_scale) = self.screen_size;
+        let canvas_w = screen_w as i32;
+        let canvas_h = (screen_h - toolbar_scale * (TOOLBAR_BUTTON_HEIGHT + 6)) as i32;
+
+        self.board_scale = MAX_ZOOM;
+        loop {
+            let (edge_x, edge_y) = self.clue_edges();
+            let w = edge_x + self.scaled(self.board_pixel_width + 2) as i32;
+            let h = edge_y + self.scaled(self.board_pixel_height + 2) as i32;
+
+            if (w <= canvas_w && h <= canvas_h) || self.board_scale <= MIN_ZOOM {
+                self.offset_x = edge_x + (canvas_w - w) / 2;
+                self.offset_y = edge_y + (canvas_h - h) / 2;
+                break;
+            }
+
+            self.board_scale = self.board_scale - ZOOM_STEP;
+        }
+    }
 }
 
-fn convert_mouse_coord_to_tile_coord(board: &Board, scale: u32, mx: i32, my: i32)
+fn convert_mouse_coord_to_tile_coord(board: &Board, scale: f32, mx: i32, my: i32)
         -> Option<(u32, u32)> {
     if mx >= 0 || my >= 0 {
-        let x_spacing = scale * (TILE_WIDTH + 2);
-        let y_spacing = scale * (TILE_HEIGHT + 2);
+        let x_spacing = scale_pixels(scale, TILE_WIDTH + 2);
+        let y_spacing = scale_pixels(scale, TILE_HEIGHT + 2);
         let tx = (mx as u32) / x_spacing;
         let ty = (my as u32) / y_spacing;
 
@@ -840,6 +2151,41 @@ fn convert_mouse_coord_to_tile_coord(board: &Board, scale: u32, mx: i32, my: i32
     None
 }
 
+// Whole pixels, so that tiles and grid lines line up at any zoom.
+fn scale_pixels(scale: f32, v: u32) -> u32 {
+    (scale * v as f32).round() as u32
+}
+
+// Tiles on a straight line between two tiles, by Bresenham's algorithm.
+fn line_tiles(x1: u32, y1: u32, x2: u32, y2: u32) -> Vec<(u32, u32)> {
+    let (mut x, mut y) = (x1 as i32, y1 as i32);
+    let dx = (x2 as i32 - x).abs();
+    let dy = -(y2 as i32 - y).abs();
+    let step_x = if x < x2 as i32 { 1 } else { -1 };
+    let step_y = if y < y2 as i32 { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut tiles = Vec::new();
+
+    loop {
+        tiles.push((x as u32, y as u32));
+        if x == x2 as i32 && y == y2 as i32 {
+            break
+        }
+
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err = err + dy;
+            x = x + step_x;
+        }
+        if e2 <= dx {
+            err = err + dx;
+            y = y + step_y;
+        }
+    }
+
+    tiles
+}
+
 fn calc_rule_width(rule: &Rule) -> u32 {
     let x_spacing = 5;
     let num_rules = rule.len() as u32;
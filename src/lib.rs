@@ -0,0 +1,41 @@
+// lib.rs
+//
+// The game logic (boards, puzzle files and the solver) is usable without
+// SDL.  The SDL front-end is only built with the "gui" feature.
+
+#[cfg(feature = "gui")]
+extern crate sdl2;
+
+#[cfg(all(feature = "gui", feature = "flic"))]
+extern crate flic;
+
+pub mod action;
+pub mod board;
+pub mod daily;
+pub mod generator;
+pub mod paths;
+pub mod puzzle;
+pub mod solver;
+
+#[cfg(feature = "gui")]
+pub mod backend;
+#[cfg(feature = "gui")]
+pub mod bindings;
+#[cfg(feature = "gui")]
+pub mod game;
+#[cfg(feature = "gui")]
+pub mod gfx;
+#[cfg(feature = "gui")]
+pub mod gui;
+#[cfg(feature = "gui")]
+pub mod replay;
+#[cfg(feature = "gui")]
+pub mod sdl_backend;
+#[cfg(feature = "gui")]
+pub mod settings;
+#[cfg(feature = "gui")]
+pub mod soft_backend;
+#[cfg(feature = "gui")]
+pub mod text;
+#[cfg(feature = "gui")]
+pub mod theme;
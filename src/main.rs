@@ -1,57 +1,32 @@
 // main.rs
 
-extern crate sdl2;
+extern crate play_picross;
 
-#[cfg(feature = "flic")]
-extern crate flic;
-
-mod action;
-mod board;
-mod gfx;
-mod gui;
-mod puzzle;
+mod cli;
+#[cfg(feature = "gui")]
+mod play;
 
 use std::env;
-
-use action::PicrossAction;
-use gui::Gui;
-use puzzle::Puzzle;
+use std::process;
 
 fn main() {
-    let mut gui = Gui::new();
-    let mut puzzle = Puzzle::new(10, 10);
-    let mut quit = false;
-
-    if env::args().count() > 1 {
-        let filename = env::args().nth(1).unwrap();
-        if let Some(p) = Puzzle::load_file(&filename) {
-            puzzle = p;
-        }
+    // subcommands run without initialising SDL
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        process::exit(code);
     }
 
-    gui.on_new_puzzle(&puzzle);
-
-    while !quit {
-        match gui.read_input(puzzle.get_board()) {
-            PicrossAction::NoOp => {},
-            PicrossAction::Quit => quit = true,
-
-            PicrossAction::New(filename) =>
-                if let Some(p) = Puzzle::load_file(&filename) {
-                    puzzle = p;
-                    gui.on_new_puzzle(&puzzle);
-                },
-
-            PicrossAction::Undo => puzzle.undo(),
-            PicrossAction::Redo => puzzle.redo(),
-            PicrossAction::Update(new_b) => puzzle.update(new_b),
+    play();
+}
 
-            PicrossAction::AutoFill =>
-                if let Some(new_b) = puzzle.get_board().autofill(puzzle.get_rules()) {
-                    puzzle.update(new_b);
-                }
-        }
+#[cfg(feature = "gui")]
+fn play() {
+    play::play();
+}
 
-        gui.draw_to_screen(puzzle.get_rules(), puzzle.get_board());
-    }
+#[cfg(not(feature = "gui"))]
+fn play() {
+    eprintln!("Built without the gui feature, only these commands are available:\n{}",
+            cli::USAGE);
+    process::exit(2);
 }
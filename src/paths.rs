@@ -0,0 +1,38 @@
+// paths.rs
+//
+// Where files are kept between games, following the XDG base directory
+// specification.
+
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Clone,Copy)]
+pub enum XdgDir {
+    // $XDG_CONFIG_HOME, or ~/.config
+    Config,
+    // $XDG_DATA_HOME, or ~/.local/share
+    Data
+}
+
+// $XDG_..._HOME/play_picross/name, or None if there is no home directory.
+pub fn xdg_file(kind: XdgDir, name: &str) -> Option<PathBuf> {
+    let (var, fallback): (&str, &[&str]) = match kind {
+        XdgDir::Config => ("XDG_CONFIG_HOME", &[".config"]),
+        XdgDir::Data => ("XDG_DATA_HOME", &[".local", "share"])
+    };
+
+    let mut path = match env::var_os(var) {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let mut home = PathBuf::from(env::var_os("HOME")?);
+            for d in fallback {
+                home.push(d);
+            }
+            home
+        }
+    };
+
+    path.push("play_picross");
+    path.push(name);
+    Some(path)
+}
@@ -0,0 +1,189 @@
+// play.rs
+//
+// Playing in a window.
+
+use std::env;
+use std::process;
+
+use play_picross::bindings::Bindings;
+use play_picross::daily::Date;
+use play_picross::game;
+use play_picross::game::Game;
+use play_picross::generator;
+use play_picross::generator::{Difficulty,GeneratorOptions};
+use play_picross::gui::Gui;
+use play_picross::puzzle::Puzzle;
+use play_picross::settings::Settings;
+
+use cli;
+
+const USAGE: &'static str = "\
+Usage: play_picross [--record EVENTS] [puzzle file]
+       play_picross --random [--size WxH] [--density D]
+                    [--difficulty easy|medium|hard] [--seed N]
+       play_picross --daily [YYYY-MM-DD]";
+
+enum StartPuzzle {
+    Blank,
+    File(String),
+    // Random(seed)
+    Random(u64),
+    Daily(Date)
+}
+
+pub fn play() {
+    let mut puzzle = Puzzle::new(10, 10);
+    let mut random_opts = GeneratorOptions::new(10, 10);
+    let mut daily = None;
+    let mut record = None;
+
+    let start = match parse_args(&mut random_opts, &mut record) {
+        Ok(s) => s,
+        Err(msg) => {
+            println!("{}\n{}\n{}", msg, USAGE, cli::USAGE);
+            process::exit(1);
+        }
+    };
+
+    match start {
+        StartPuzzle::Blank => {},
+
+        StartPuzzle::File(filename) =>
+            match Puzzle::load_file(&filename) {
+                Ok(p) => puzzle = p,
+                Err(e) => println!("{}: {}", filename, e)
+            },
+
+        StartPuzzle::Random(seed) =>
+            if let Some(p) = game::generate_random(&random_opts, seed) {
+                puzzle = p;
+            },
+
+        StartPuzzle::Daily(date) =>
+            if let Some(p) = game::start_daily(date) {
+                puzzle = p;
+                daily = Some(date);
+            }
+    }
+
+    let mut settings = Settings::load_user();
+    let mut gui = Gui::new(&settings);
+    gui.set_bindings(Bindings::load_user());
+    if let Some(filename) = record {
+        if let Err(e) = gui.record_to(&filename) {
+            println!("{}: {}", filename, e);
+            process::exit(1);
+        }
+    }
+
+    let mut game = Game::new(puzzle, random_opts);
+    game.set_auto_cross(settings.auto_cross);
+    if let Some(date) = daily {
+        game.set_daily(date);
+    }
+    game.run(&mut gui);
+
+    gui.store_settings(&mut settings);
+    settings.auto_cross = game.get_auto_cross();
+    if let Err(e) = settings.save_user() {
+        println!("Could not save settings: {}", e);
+    }
+}
+
+fn parse_args(random_opts: &mut GeneratorOptions, record: &mut Option<String>)
+        -> Result<StartPuzzle, String> {
+    let mut filename = None;
+    let mut random = false;
+    let mut seed = None;
+    let mut daily = None;
+    let mut args = env::args().skip(1).peekable();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--random" => random = true,
+
+            "--record" =>
+                *record = Some(args.next().ok_or(format!("Missing value for {}", arg))?),
+
+            "--daily" => {
+                let date =
+                    match args.peek() {
+                        Some(value) if !value.starts_with("-") =>
+                            Some(Date::parse(value).ok_or(
+                                    format!("Invalid date: {}", value))?),
+                        _ => None
+                    };
+
+                if date.is_some() {
+                    args.next();
+                }
+                daily = Some(date.unwrap_or_else(Date::today));
+            },
+
+            "--size" | "--density" | "--difficulty" | "--seed" => {
+                let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+                let invalid = format!("Invalid value for {}: {}", arg, value);
+                random = true;
+
+                match arg.as_str() {
+                    "--size" => {
+                        let (w, h) = parse_size(&value).ok_or(invalid)?;
+                        random_opts.width = w;
+                        random_opts.height = h;
+                    },
+
+                    "--density" =>
+                        match value.parse::<f64>() {
+                            Ok(d) if d >= 0.0 && d <= 1.0 => random_opts.density = d,
+                            _ => return Err(invalid)
+                        },
+
+                    "--difficulty" =>
+                        random_opts.difficulty =
+                            Some(Difficulty::parse(&value).ok_or(invalid)?),
+
+                    _ =>
+                        seed = Some(value.parse::<u64>().map_err(|_| invalid)?)
+                }
+            },
+
+            _ =>
+                if arg.starts_with("-") || filename.is_some() {
+                    return Err(format!("Unexpected argument: {}", arg))
+                } else {
+                    filename = Some(arg)
+                }
+        }
+    }
+
+    let num_sources = filename.iter().count() + daily.iter().count()
+        + if random { 1 } else { 0 };
+    if num_sources > 1 {
+        return Err(String::from("Choose one of a puzzle file, --random or --daily"))
+    }
+
+    if let Some(f) = filename {
+        Ok(StartPuzzle::File(f))
+    } else if let Some(date) = daily {
+        Ok(StartPuzzle::Daily(date))
+    } else if random {
+        Ok(StartPuzzle::Random(seed.unwrap_or_else(generator::random_seed)))
+    } else {
+        Ok(StartPuzzle::Blank)
+    }
+}
+
+// Parse "WxH".
+fn parse_size(s: &str) -> Option<(usize, usize)> {
+    let ws: Vec<&str> = s.splitn(2, 'x').collect();
+    if ws.len() == 2 {
+        if let (Ok(w), Ok(h)) = (ws[0].trim().parse::<usize>(), ws[1].trim().parse::<usize>()) {
+            if w > 0 && h > 0 {
+                return Some((w, h))
+            }
+        }
+    }
+
+    None
+}
+
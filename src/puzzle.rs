@@ -6,6 +6,7 @@ use std::fs::File;
 use std::io;
 use std::io::BufRead;
 use std::io::BufReader;
+use std::io::Write;
 use std::num;
 use std::path::Path;
 
@@ -15,20 +16,53 @@ type History = Vec<Board>;
 pub type Rule = Vec<u32>;
 pub type Rules<'a> = (&'a Vec<Rule>, &'a Vec<Rule>);
 
-// PuzzleReaderResult(width, height, col_rules, row_rules)
-type PuzzleReaderResult = (usize, usize, Vec<Rule>, Vec<Rule>);
+#[derive(Clone,Copy,Eq,PartialEq)]
+enum Section {
+    Rows,
+    Columns
+}
+
+// What was read from a puzzle file, with the line each rule came from.
+struct PuzzleFile {
+    width: usize,
+    height: usize,
+    col_rules: Vec<Rule>,
+    row_rules: Vec<Rule>,
+    col_lines: Vec<usize>,
+    row_lines: Vec<usize>
+}
 
 #[derive(Debug)]
-enum PuzzleReaderError {
+pub enum PuzzleReaderError {
     Io(io::Error),
     ParseInt(num::ParseIntError),
 
-    ParseError(String, Box<PuzzleReaderError>),
+    // ParseError(line number, column, line, error), counting from 1
+    ParseError(usize, usize, String, Box<PuzzleReaderError>),
 
+    MalformedDimensions,
     InvalidDimensions(usize, usize),
     IncompletePuzzle,
-    TooManyRules,
-    RuleTooLong,
+    // MissingRows(expected, found)
+    MissingRows(usize, usize),
+    MissingColumns(usize, usize),
+    // TooManyRows(height)
+    TooManyRows(usize),
+    // RowTooLong(row, tiles needed, width), counting rows from 1
+    RowTooLong(usize, u32, usize),
+    // ColumnTooLong(column, tiles needed, height)
+    ColumnTooLong(usize, u32, usize),
+    ZeroInRule,
+    UnexpectedContent,
+}
+
+// One number of a rule.
+#[derive(Clone,Copy,Eq,Hash,PartialEq)]
+pub enum Clue {
+    // Column(x, index into the rule)
+    Column(usize, usize),
+    // Row(y, index into the rule)
+    Row(usize, usize)
 }
 
 pub struct Puzzle {
@@ -68,22 +102,134 @@ impl Puzzle {
         }
     }
 
-    pub fn load_file(filename: &String) -> Option<Puzzle> {
-        match read_file(filename) {
-            Ok((width, height, col_rules, row_rules)) =>
-                Some(Puzzle::new_with_rules(width, height, col_rules, row_rules)),
+    // Puzzle whose rules describe the filled tiles of the given board.
+    pub fn from_solution(board: &Board) -> Puzzle {
+        let (col_rules, row_rules) = derive_rules(board);
+        Puzzle::new_with_rules(board.width, board.height, col_rules, row_rules)
+    }
 
-            Err(e) => {
-                println!("{}: {}", filename, e);
-                None
+    pub fn load_file(filename: &String) -> Result<Puzzle, PuzzleReaderError> {
+        let f = read_file(filename)?;
+        Ok(Puzzle::new_with_rules(f.width, f.height, f.col_rules, f.row_rules))
+    }
+
+    // Check a puzzle file for clues that cannot all be right, without
+    // solving it.  Returns a warning for each problem found.
+    pub fn lint_file(filename: &String) -> Result<Vec<String>, PuzzleReaderError> {
+        let f = read_file(filename)?;
+        let mut warnings = Vec::new();
+
+        let row_total = clue_total(f.row_rules.iter().flat_map(|r| r.iter()));
+        let col_total = clue_total(f.col_rules.iter().flat_map(|r| r.iter()));
+        match (row_total, col_total) {
+            (Some(r), Some(c)) =>
+                if r != c {
+                    warnings.push(format!("rows fill {} tiles but columns fill {}", r, c));
+                },
+            _ => warnings.push(String::from("clues fill more tiles than can be counted"))
+        }
+
+        // each filled tile of a row needs a column with something in it
+        let num_cols = f.col_rules.iter().filter(|r| !r.is_empty()).count() as u32;
+        let num_rows = f.row_rules.iter().filter(|r| !r.is_empty()).count() as u32;
+
+        for (y, rule) in f.row_rules.iter().enumerate() {
+            let sum = clue_total(rule.iter()).unwrap_or(u32::MAX);
+            if sum > num_cols {
+                warnings.push(format!("line {}: row {} fills {} tiles but only {} columns have clues",
+                        f.row_lines[y], y + 1, sum, num_cols));
             }
         }
+
+        for (x, rule) in f.col_rules.iter().enumerate() {
+            let sum = clue_total(rule.iter()).unwrap_or(u32::MAX);
+            if sum > num_rows {
+                warnings.push(format!("line {}: column {} fills {} tiles but only {} rows have clues",
+                        f.col_lines[x], x + 1, sum, num_rows));
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    // Files ending in .non are written in the format used by other
+    // nonogram programs, everything else in our own format.
+    pub fn save_file(&self, filename: &String) -> io::Result<()> {
+        let path = Path::new(filename);
+        let mut file = File::create(path)?;
+
+        if path.extension().and_then(|ext| ext.to_str()) == Some("non") {
+            self.write_non(&mut file)
+        } else {
+            self.write_native(&mut file)
+        }
+    }
+
+    fn write_native<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "# width x height")?;
+        writeln!(w, "{} x {}", self.width, self.height)?;
+        writeln!(w)?;
+        writeln!(w, "# rows")?;
+        for rule in self.row_rules.iter() {
+            writeln!(w, "{}", format_rule(rule, " "))?;
+        }
+        writeln!(w)?;
+        writeln!(w, "# columns")?;
+        for rule in self.col_rules.iter() {
+            writeln!(w, "{}", format_rule(rule, " "))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_non<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "width {}", self.width)?;
+        writeln!(w, "height {}", self.height)?;
+        writeln!(w)?;
+        writeln!(w, "rows")?;
+        for rule in self.row_rules.iter() {
+            writeln!(w, "{}", format_rule(rule, ","))?;
+        }
+        writeln!(w)?;
+        writeln!(w, "columns")?;
+        for rule in self.col_rules.iter() {
+            writeln!(w, "{}", format_rule(rule, ","))?;
+        }
+
+        Ok(())
+    }
+
+    // Replace the rules with those described by the current board.
+    // Used by the editor, where the board is the solution being drawn.
+    pub fn derive_rules_from_board(&mut self) {
+        let (col_rules, row_rules) = derive_rules(self.get_board());
+        self.col_rules = col_rules;
+        self.row_rules = row_rules;
+    }
+
+    // Puzzle with new dimensions, starting from a resized copy of the board.
+    pub fn resize(&self, width: usize, height: usize) -> Puzzle {
+        let mut p = Puzzle::new(width, height);
+        p.history[0] = self.get_board().resize(width, height);
+        p.derive_rules_from_board();
+        p
     }
 
     pub fn get_rules(&self) -> Rules {
         (&self.col_rules, &self.row_rules)
     }
 
+    pub fn is_done(&self, clue: Clue) -> bool {
+        self.get_board().is_done(clue)
+    }
+
+    // Marking a clue is a step in the history, like painting a tile.
+    pub fn toggle_done(&mut self, clue: Clue) {
+        let mut b = self.get_board().clone();
+        b.toggle_done(clue);
+        self.update(b);
+    }
+
     pub fn get_board(&self) -> &Board {
         &self.history[self.curr_history]
     }
@@ -111,109 +257,258 @@ impl Puzzle {
     }
 }
 
-fn read_file(filename: &String) -> Result<PuzzleReaderResult, PuzzleReaderError> {
-    let path = Path::new(filename);
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+fn read_file(filename: &String) -> Result<PuzzleFile, PuzzleReaderError> {
+    let file = File::open(Path::new(filename))?;
+    read_puzzle(BufReader::new(file))
+}
+
+fn read_puzzle<R: BufRead>(reader: R) -> Result<PuzzleFile, PuzzleReaderError> {
     let mut dim: Option<(usize,usize)> = None;
+    // (width, height) from "width N" and "height N" lines
+    let mut named_dim: (Option<usize>, Option<usize>) = (None, None);
+    // Some(section) after a "rows:" or "columns:" line
+    let mut section = None;
     let mut row_rules = Vec::new();
     let mut col_rules = Vec::new();
+    let mut row_lines = Vec::new();
+    let mut col_lines = Vec::new();
 
-    for line in reader.lines() {
+    for (i, line) in reader.lines().enumerate() {
         if let Err(e) = line {
             return Err(PuzzleReaderError::Io(e))
         }
 
         let ln = line.unwrap();
-        if ln.is_empty() || ln.starts_with("#") {
+        let line_num = i + 1;
+        if ln.trim().is_empty() || ln.starts_with("#") || is_non_metadata(&ln) {
             continue
         }
 
+        // Add some context.
+        let parse_error = |column: usize, e: PuzzleReaderError|
+            PuzzleReaderError::ParseError(line_num, column, ln.clone(), Box::new(e));
+
         if dim.is_none() {
-            let ws: Vec<&str> = ln.splitn(2, 'x').collect();
-            if ws.len() == 2 {
-                let possible_w = ws[0].trim().parse::<usize>();
-                let possible_h = ws[1].trim().parse::<usize>();
-                if possible_w.is_ok() && possible_h.is_ok() {
-                    let w = possible_w.unwrap();
-                    let h = possible_h.unwrap();
-
-                    if w <= 0 || h <= 0 {
-                        return Err(PuzzleReaderError::InvalidDimensions(w, h))
-                    }
-
-                    dim = Some((w, h));
-                }
-                if dim.is_none() {
-                    break
-                }
+            match read_named_dimension(&ln) {
+                Some(("width", w)) => named_dim.0 = Some(w),
+                Some((_, h)) => named_dim.1 = Some(h),
+                // other .non keywords before the size are not needed
+                None if is_unknown_keyword(&ln) => continue,
+                None => {}
             }
-        } else {
-            let (width, height) = dim.unwrap();
 
-            let (max_value, max_elements) =
-                if row_rules.len() < height {
-                    (width, height)
+            let (w, h) =
+                if let (Some(w), Some(h)) = named_dim {
+                    (w, h)
+                } else if named_dim.0.is_some() || named_dim.1.is_some() {
+                    continue
                 } else {
-                    (height, width)
+                    read_dimensions(&ln).ok_or_else(||
+                            parse_error(1, PuzzleReaderError::MalformedDimensions))?
                 };
 
-            // Add some context.
-            let rules =
-                read_rules(&ln, max_value, max_elements).map_err(|e|
-                        PuzzleReaderError::ParseError(ln.clone(), Box::new(e)))?;
+            if w == 0 || h == 0 {
+                return Err(parse_error(1, PuzzleReaderError::InvalidDimensions(w, h)))
+            }
 
-            if !rules.is_empty() {
-                if row_rules.len() < height {
-                    row_rules.push(rules);
-                } else {
-                    col_rules.push(rules);
-                }
+            dim = Some((w, h));
+            continue
+        }
+
+        if let Some(s) = read_section(&ln) {
+            section = Some(s);
+            continue
+        }
+
+        let (width, height) = dim.unwrap();
+
+        // without section lines, the rows come first
+        let is_row =
+            match section {
+                Some(s) => s == Section::Rows,
+                None => row_rules.len() < height
+            };
+
+        if is_row && row_rules.len() >= height {
+            return Err(parse_error(1, PuzzleReaderError::TooManyRows(height)))
+        }
+        if !is_row && col_rules.len() >= width {
+            let column = column_of(&ln, ln.trim_start());
+            return Err(parse_error(column, PuzzleReaderError::UnexpectedContent))
+        }
+
+        let rules = read_rules(&ln, line_num)?;
+        // clues too big to add up are too long for any line
+        let needed = tiles_needed(&rules).unwrap_or(u32::MAX);
+
+        if is_row {
+            if needed as u64 > width as u64 {
+                return Err(parse_error(1,
+                        PuzzleReaderError::RowTooLong(row_rules.len() + 1, needed, width)))
             }
+            row_rules.push(rules);
+            row_lines.push(line_num);
+        } else {
+            if needed as u64 > height as u64 {
+                return Err(parse_error(1,
+                        PuzzleReaderError::ColumnTooLong(col_rules.len() + 1, needed, height)))
+            }
+            col_rules.push(rules);
+            col_lines.push(line_num);
+        }
+    }
+
+    let (width, height) = match dim {
+        Some(d) => d,
+        None => return Err(PuzzleReaderError::IncompletePuzzle)
+    };
 
-            if col_rules.len() >= height {
-                break
+    if row_rules.len() < height {
+        return Err(PuzzleReaderError::MissingRows(height, row_rules.len()))
+    }
+    if col_rules.len() < width {
+        return Err(PuzzleReaderError::MissingColumns(width, col_rules.len()))
+    }
+
+    Ok(PuzzleFile {
+        width,
+        height,
+        col_rules,
+        row_rules,
+        col_lines,
+        row_lines
+    })
+}
+
+// Parse "width N" or "height N", as used by .non files.
+fn read_named_dimension(ln: &str) -> Option<(&'static str, usize)> {
+    let ws: Vec<&str> = ln.split_whitespace().collect();
+    if ws.len() == 2 {
+        if let Ok(v) = ws[1].parse::<usize>() {
+            match ws[0] {
+                "width" => return Some(("width", v)),
+                "height" => return Some(("height", v)),
+                _ => {}
             }
         }
     }
 
-    if let Some((width, height)) = dim {
-        if row_rules.len() == height && col_rules.len() == width {
-            return Ok((width, height, col_rules, row_rules))
+    None
+}
+
+// .non files describe the puzzle with lines such as 'title "..."',
+// which are not needed to play it.
+fn is_non_metadata(ln: &str) -> bool {
+    match ln.split_whitespace().next() {
+        Some(key) => NON_METADATA.contains(&key.to_lowercase().as_str()),
+        None => false
+    }
+}
+
+const NON_METADATA: [&str; 7] =
+    ["title", "catalogue", "by", "author", "copyright", "license", "goal"];
+
+// A line starting with a word other than width or height.
+fn is_unknown_keyword(ln: &str) -> bool {
+    match ln.split_whitespace().next() {
+        Some(key) =>
+            key.starts_with(|c: char| c.is_alphabetic())
+            && key != "width" && key != "height",
+        None => false
+    }
+}
+
+// Parse "rows" or "columns", with an optional colon.
+fn read_section(ln: &str) -> Option<Section> {
+    let name = ln.trim().trim_end_matches(':').to_lowercase();
+    match name.as_str() {
+        "rows" => Some(Section::Rows),
+        "columns" | "cols" => Some(Section::Columns),
+        _ => None
+    }
+}
+
+// Parse "W x H".
+fn read_dimensions(ln: &str) -> Option<(usize, usize)> {
+    let ws: Vec<&str> = ln.splitn(2, 'x').collect();
+    if ws.len() == 2 {
+        if let (Ok(w), Ok(h)) = (ws[0].trim().parse::<usize>(), ws[1].trim().parse::<usize>()) {
+            return Some((w, h))
         }
     }
 
-    Err(PuzzleReaderError::IncompletePuzzle)
+    None
 }
 
-fn read_rules(ln: &String, max_value: usize, max_elements: usize)
-    -> Result<Rule, PuzzleReaderError>
-{
-    let maybe_vs: Vec<&str> = ln.split_whitespace().collect();
+// Clues may be separated by spaces or commas.
+fn read_rules(ln: &str, line_num: usize) -> Result<Rule, PuzzleReaderError> {
+    let maybe_vs: Vec<&str> = ln
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect();
     let mut rules = Vec::new();
-    let mut sum = 0;
+
+    // Add some context.
+    let parse_error = |word: &str, e: PuzzleReaderError|
+        PuzzleReaderError::ParseError(line_num, column_of(ln, word), ln.to_string(), Box::new(e));
 
     for maybe_v in maybe_vs.iter() {
-        let v = maybe_v.parse::<u32>()?;
+        let v = maybe_v.parse::<u32>().map_err(|e| parse_error(maybe_v, e.into()))?;
+        if v == 0 && maybe_vs.len() > 1 {
+            return Err(parse_error(maybe_v, PuzzleReaderError::ZeroInRule))
+        }
         rules.push(v);
-        sum = sum + v;
     }
 
-    if rules.len() == 0 {
-        // do not fail for lines with no numbers
-        return Ok(rules)
+    // a single 0 marks a line with no filled tiles
+    if rules == [0] {
+        rules.clear();
     }
 
-    if rules.len() * 2 - 1 > max_elements {
-        return Err(PuzzleReaderError::TooManyRules)
-    }
+    Ok(rules)
+}
+
+// Tiles a rule needs, with a gap between blocks, or None if that is
+// too many to count.
+fn tiles_needed(rule: &[u32]) -> Option<u32> {
+    let gaps = rule.len().saturating_sub(1) as u32;
+    rule.iter().try_fold(gaps, |sum, &v| sum.checked_add(v))
+}
+
+// Sum of the clues, or None if that is too many to count.
+fn clue_total<'a, I: Iterator<Item = &'a u32>>(mut clues: I) -> Option<u32> {
+    clues.try_fold(0u32, |sum, &v| sum.checked_add(v))
+}
+
+// Column of a word taken from the line, counting from 1.
+fn column_of(ln: &str, word: &str) -> usize {
+    let offset = word.as_ptr() as usize - ln.as_ptr() as usize;
+    ln[..offset].chars().count() + 1
+}
 
-    sum = sum + (rules.len() as u32 - 1);
-    if sum > max_value as u32 {
-        return Err(PuzzleReaderError::RuleTooLong)
+fn derive_rules(board: &Board) -> (Vec<Rule>, Vec<Rule>) {
+    let col_rules = (0..board.width).map(|x| board.get_column_segments(x)).collect();
+    let row_rules = (0..board.height).map(|y| board.get_row_segments(y)).collect();
+    (col_rules, row_rules)
+}
+
+fn format_rule(rule: &Rule, separator: &str) -> String {
+    if rule.is_empty() {
+        return String::from("0")
     }
 
-    Ok(rules)
+    let vs: Vec<String> = rule.iter().map(|v| v.to_string()).collect();
+    vs.join(separator)
+}
+
+impl PuzzleReaderError {
+    // (line number, column) where the error was found, if known.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match *self {
+            PuzzleReaderError::ParseError(line_num, column, ..) => Some((line_num, column)),
+            _ => None
+        }
+    }
 }
 
 impl fmt::Display for PuzzleReaderError {
@@ -225,15 +520,34 @@ impl fmt::Display for PuzzleReaderError {
             PuzzleReaderError::ParseInt(ref err) =>
                 write!(f, "Parse error: {}", err),
 
-            PuzzleReaderError::ParseError(ref ln, ref err) =>
-                write!(f, "Error on line '{}': {}", ln, err),
+            PuzzleReaderError::ParseError(line_num, column, ref ln, ref err) =>
+                write!(f, "Error on line {}, column {} ('{}'): {}",
+                        line_num, column, ln, err),
 
             PuzzleReaderError::InvalidDimensions(w, h) =>
                 write!(f, "Invalid dimensions ({} x {})", w, h),
 
-            PuzzleReaderError::IncompletePuzzle
-            | PuzzleReaderError::RuleTooLong
-            | PuzzleReaderError::TooManyRules =>
+            PuzzleReaderError::MissingRows(expected, found) =>
+                write!(f, "Expected {} rows, got {}", expected, found),
+
+            PuzzleReaderError::MissingColumns(expected, found) =>
+                write!(f, "Expected {} columns, got {}", expected, found),
+
+            PuzzleReaderError::TooManyRows(height) =>
+                write!(f, "More than {} rows", height),
+
+            PuzzleReaderError::RowTooLong(y, needed, width) =>
+                write!(f, "Clues in row {} need {} tiles, more than the width of {}",
+                        y, needed, width),
+
+            PuzzleReaderError::ColumnTooLong(x, needed, height) =>
+                write!(f, "Clues in column {} need {} tiles, more than the height of {}",
+                        x, needed, height),
+
+            PuzzleReaderError::MalformedDimensions
+            | PuzzleReaderError::IncompletePuzzle
+            | PuzzleReaderError::ZeroInRule
+            | PuzzleReaderError::UnexpectedContent =>
                 write!(f, "{}", error::Error::description(self)),
         }
     }
@@ -251,17 +565,33 @@ impl error::Error for PuzzleReaderError {
             PuzzleReaderError::ParseError(..) =>
                 "Error parsing line",
 
+            PuzzleReaderError::MalformedDimensions =>
+                "Expected the dimensions, as width x height",
+
             PuzzleReaderError::InvalidDimensions(..) =>
                 "Invalid dimensions",
 
             PuzzleReaderError::IncompletePuzzle =>
-                "Puzzle incomplete",
+                "Puzzle incomplete, no dimensions found",
+
+            PuzzleReaderError::MissingRows(..) =>
+                "Not enough rows",
+
+            PuzzleReaderError::MissingColumns(..) =>
+                "Not enough columns",
+
+            PuzzleReaderError::TooManyRows(..) =>
+                "Too many rows",
 
-            PuzzleReaderError::TooManyRules =>
-                "Too many rules for board dimensions",
+            PuzzleReaderError::RowTooLong(..)
+            | PuzzleReaderError::ColumnTooLong(..) =>
+                "Rule length exceeds board dimensions",
 
-            PuzzleReaderError::RuleTooLong =>
-                "Rule length exceeds for board dimensions"
+            PuzzleReaderError::ZeroInRule =>
+                "0 can only be used alone, for an empty line",
+
+            PuzzleReaderError::UnexpectedContent =>
+                "Unexpected content after the columns"
         }
     }
 
@@ -270,13 +600,19 @@ impl error::Error for PuzzleReaderError {
             PuzzleReaderError::Io(ref err) => Some(err),
             PuzzleReaderError::ParseInt(ref err) => Some(err),
 
-            PuzzleReaderError::ParseError(_, ref box_err) =>
+            PuzzleReaderError::ParseError(_, _, _, ref box_err) =>
                 Some(&**box_err),
 
-            PuzzleReaderError::InvalidDimensions(..)
+            PuzzleReaderError::MalformedDimensions
+            | PuzzleReaderError::InvalidDimensions(..)
             | PuzzleReaderError::IncompletePuzzle
-            | PuzzleReaderError::TooManyRules
-            | PuzzleReaderError::RuleTooLong => None
+            | PuzzleReaderError::MissingRows(..)
+            | PuzzleReaderError::MissingColumns(..)
+            | PuzzleReaderError::TooManyRows(..)
+            | PuzzleReaderError::RowTooLong(..)
+            | PuzzleReaderError::ColumnTooLong(..)
+            | PuzzleReaderError::ZeroInRule
+            | PuzzleReaderError::UnexpectedContent => None
         }
     }
 }
@@ -292,3 +628,150 @@ impl From<num::ParseIntError> for PuzzleReaderError {
         PuzzleReaderError::ParseInt(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_str(s: &str) -> Result<PuzzleFile, PuzzleReaderError> {
+        read_puzzle(s.as_bytes())
+    }
+
+    // The line, column and error of a ParseError.
+    fn parse_error(s: &str) -> (usize, usize, PuzzleReaderError) {
+        match read_str(s) {
+            Err(PuzzleReaderError::ParseError(line, column, _, e)) => (line, column, *e),
+            Err(e) => panic!("not a parse error: {}", e),
+            Ok(_) => panic!("puzzle accepted: {:?}", s)
+        }
+    }
+
+    #[test]
+    fn errors_have_line_and_column() {
+        use super::PuzzleReaderError::*;
+
+        match parse_error("# size\n1 by 1\n") {
+            (2, 1, MalformedDimensions) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+        match parse_error("0 x 1\n") {
+            (1, 1, InvalidDimensions(0, 1)) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+        match parse_error("2 x 1\n1 a\n") {
+            (2, 3, ParseInt(_)) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+        match parse_error("2 x 1\n1, 0\n") {
+            (2, 4, ZeroInRule) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+        match parse_error("1 x 1\nrows\n1\n\n1\n") {
+            (5, 1, TooManyRows(1)) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+        match parse_error("2 x 1\n1 1\n") {
+            (2, 1, RowTooLong(1, 3, 2)) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+        match parse_error("1 x 1\n1\n1 1\n") {
+            (3, 1, ColumnTooLong(1, 3, 1)) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+        match parse_error("1 x 1\n1\n1\n  more\n") {
+            (4, 3, UnexpectedContent) => {},
+            (l, c, e) => panic!("{}:{}: {}", l, c, e)
+        }
+    }
+
+    #[test]
+    fn errors_without_a_line() {
+        use super::PuzzleReaderError::*;
+
+        match read_str("# nothing here\n") {
+            Err(IncompletePuzzle) => {},
+            r => panic!("wrong result: {:?}", r.err().map(|e| e.to_string()))
+        }
+        match read_str("1 x 2\n1\n") {
+            Err(MissingRows(2, 1)) => {},
+            r => panic!("wrong result: {:?}", r.err().map(|e| e.to_string()))
+        }
+        match read_str("1 x 1\n1\n") {
+            Err(MissingColumns(1, 0)) => {},
+            r => panic!("wrong result: {:?}", r.err().map(|e| e.to_string()))
+        }
+        // not UTF-8
+        match read_puzzle(&b"1 x 1\n\xff\n"[..]) {
+            Err(Io(_)) => {},
+            r => panic!("wrong result: {:?}", r.err().map(|e| e.to_string()))
+        }
+    }
+
+    #[test]
+    fn non_metadata_is_ignored() {
+        let f = read_str("catalogue \"test 1\"\ntitle \"Corner\"\nauthor someone\n\
+                copyright \"someone, 2024\"\nwidth 2\nheight 2\n\n\
+                rows\n2\n1\n\ncolumns\n2\n1\n\ngoal \"1110\"\n").unwrap();
+        assert_eq!(f.row_rules, vec![vec![2], vec![1]]);
+        assert_eq!(f.col_rules, vec![vec![2], vec![1]]);
+
+        match read_str("2 x 1\n1\n1\n1\nsubtitle \"x\"\n") {
+            Err(PuzzleReaderError::ParseError(5, 1, _, _)) => {},
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(_) => panic!("unknown line accepted")
+        }
+    }
+
+    #[test]
+    fn webpbn_export_loads() {
+        let f = read_str("\
+catalogue \"webpbn.com #1\"
+title \"Demo Puzzle from Front Page\"
+by \"Jan Wolter\"
+copyright \"&copy; Copyright 2004 by Jan Wolter\"
+license CC-BY-3.0
+description \"A small picture\"
+width 5
+height 3
+
+rows
+2
+1,1
+5
+
+columns
+1
+2
+1
+2
+1
+goal \"110000101011111\"
+").unwrap();
+        assert_eq!((f.width, f.height), (5, 3));
+        assert_eq!(f.row_rules, vec![vec![2], vec![1, 1], vec![5]]);
+        assert_eq!(f.col_rules, vec![vec![1], vec![2], vec![1], vec![2], vec![1]]);
+    }
+
+    #[test]
+    fn oversized_clue_is_an_error() {
+        match read_str("2 x 1\n4294967295 1\n1\n1\n") {
+            Err(PuzzleReaderError::ParseError(2, 1, _, ref e)) =>
+                match **e {
+                    PuzzleReaderError::RowTooLong(1, _, 2) => {},
+                    ref e => panic!("wrong error: {}", e)
+                },
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(_) => panic!("oversized clue accepted")
+        }
+
+        match read_str("1 x 2\n1\n1\n4294967295 4294967295\n") {
+            Err(PuzzleReaderError::ParseError(4, 1, _, ref e)) =>
+                match **e {
+                    PuzzleReaderError::ColumnTooLong(1, _, 2) => {},
+                    ref e => panic!("wrong error: {}", e)
+                },
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(_) => panic!("oversized clue accepted")
+        }
+    }
+}
@@ -0,0 +1,372 @@
+// replay.rs
+//
+// Recording the events read by the Gui, and playing them back without
+// a window for regression tests.
+//
+// Each line of a recording is the time in milliseconds followed by one
+// event:
+//
+//   0 resize 640 400
+//   900 focus_lost
+//   1250 key_down 0 Z
+//   1300 key_up 0 Z
+//   1400 mouse_motion 120 80 1
+//   1410 mouse_down left 120 80
+//   1600 mouse_up left 140 80
+//   1700 wheel 0 -1
+//   1800 drop puzzles/example.txt
+//   1900 finger_down 1 0.25 0.5
+//   1950 gesture 2 0.01 0.5 0.5
+//   1960 pad_down a
+//   1970 pad_up a
+//   2000 quit
+//
+// Key names are SDL key names, which may contain spaces, so they come last.
+// Fingers are given by id, from 0 to 1 across the window.  Gestures give
+// the number of fingers, the change in their distance and their centre.
+// Controller buttons are SDL game controller button names.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufRead,BufReader,BufWriter,Write};
+use std::path::Path;
+use std::str::SplitWhitespace;
+use sdl2::controller::Button;
+use sdl2::event::{Event,WindowEvent};
+use sdl2::keyboard::{Keycode,Mod};
+use sdl2::mouse::{MouseButton,MouseState,MouseWheelDirection};
+
+use backend::Atlas;
+use board::Board;
+use game::Game;
+use generator::GeneratorOptions;
+use gui::Gui;
+use puzzle::Puzzle;
+use soft_backend::SoftwareBackend;
+
+// (ticks, event)
+pub type TimedEvent = (u32, Event);
+
+// How long to keep going after the last event, so that long presses and
+// anything else waiting on the clock still happen.
+const SETTLE_TICKS: u32 = 1000;
+
+pub struct Recorder {
+    out: BufWriter<File>
+}
+
+impl Recorder {
+    // Start a recording of a window of the given size.
+    pub fn create(filename: &String, screen_w: u32, screen_h: u32)
+            -> io::Result<Recorder> {
+        let file = File::create(filename)?;
+        let mut r = Recorder { out: BufWriter::new(file) };
+
+        writeln!(r.out, "# play_picross events")?;
+        r.record(0, &Event::Window {
+                timestamp: 0,
+                window_id: 0,
+                win_event: WindowEvent::Resized(screen_w as i32, screen_h as i32) })?;
+        Ok(r)
+    }
+
+    // Events the Gui does not look at are skipped.
+    pub fn record(&mut self, ticks: u32, event: &Event) -> io::Result<()> {
+        if let Some(line) = format_event(event) {
+            writeln!(self.out, "{} {}", ticks, line)?;
+            // keep the recording if the game crashes
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn load_events(filename: &String) -> io::Result<Vec<TimedEvent>> {
+    let file = File::open(filename)?;
+    read_events(BufReader::new(file))
+}
+
+pub fn read_events<R: BufRead>(reader: R) -> io::Result<Vec<TimedEvent>> {
+    let mut events = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("#") {
+            continue;
+        }
+
+        match parse_event(line) {
+            Some(e) => events.push(e),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("line {}: cannot read event: {}", i + 1, line)))
+        }
+    }
+
+    Ok(events)
+}
+
+// Play the events against the puzzle, returning the final board.
+// With a snapshot directory, the board and the screen are saved there
+// as NNNN.txt and NNNN.ppm each time the board changes.  Nothing else
+// is written: saving puzzles and daily times is turned off.
+pub fn replay(puzzle: Puzzle, events: Vec<TimedEvent>,
+        atlas: Option<Atlas>, snapshot_dir: Option<&Path>) -> io::Result<Board> {
+    let game = replay_game(puzzle, events, atlas, snapshot_dir)?;
+    Ok(game.get_puzzle().get_board().clone())
+}
+
+// As replay, returning the game so that its history can be looked at.
+pub fn replay_game(puzzle: Puzzle, events: Vec<TimedEvent>,
+        atlas: Option<Atlas>, snapshot_dir: Option<&Path>) -> io::Result<Game> {
+    let (width, height) = (puzzle.get_board().width, puzzle.get_board().height);
+    let backend = SoftwareBackend::new(640, 400, atlas);
+    let end = events.iter().map(|&(t, _)| t).max().unwrap_or(0) + SETTLE_TICKS;
+    for (ticks, e) in events {
+        backend.push_event_at(ticks, e);
+    }
+
+    let mut gui = Gui::new_with_backend(Box::new(backend.clone()));
+    let mut game = Game::new(puzzle, GeneratorOptions::new(width, height));
+    game.set_write_files(false);
+    let mut last_board = game.get_puzzle().get_board().clone();
+    let mut num_snapshots = 0;
+
+    gui.on_new_puzzle(game.get_puzzle());
+
+    while (backend.has_events() || backend.now() < end) && game.step(&mut gui) {
+        let p = game.get_puzzle();
+        if *p.get_board() == last_board {
+            continue;
+        }
+        last_board = p.get_board().clone();
+
+        if let Some(dir) = snapshot_dir {
+            num_snapshots = num_snapshots + 1;
+
+            gui.request_redraw();
+            gui.draw_to_screen(p);
+
+            let mut f = File::create(dir.join(format!("{:04}.txt", num_snapshots)))?;
+            write!(f, "{}", p.get_board())?;
+            backend.save_ppm(&dir.join(format!("{:04}.ppm", num_snapshots)))?;
+        }
+    }
+
+    Ok(game)
+}
+
+/*--------------------------------------------------------------*/
+
+fn format_event(event: &Event) -> Option<String> {
+    match *event {
+        Event::Quit {..} =>
+            Some(String::from("quit")),
+
+        Event::Window { win_event: WindowEvent::Resized(w, h), .. } =>
+            Some(format!("resize {} {}", w, h)),
+
+        Event::Window { win_event: WindowEvent::FocusLost, .. } =>
+            Some(String::from("focus_lost")),
+
+        Event::KeyDown { keycode: Some(k), keymod, .. } =>
+            Some(format!("key_down {} {}", keymod.bits(), k.name())),
+
+        Event::KeyUp { keycode: Some(k), keymod, .. } =>
+            Some(format!("key_up {} {}", keymod.bits(), k.name())),
+
+        Event::MouseMotion { x, y, mousestate, .. } =>
+            Some(format!("mouse_motion {} {} {}", x, y, mousestate.to_sdl_state())),
+
+        Event::MouseButtonDown { mouse_btn, x, y, .. } =>
+            format_button(mouse_btn).map(|b| format!("mouse_down {} {} {}", b, x, y)),
+
+        Event::MouseButtonUp { mouse_btn, x, y, .. } =>
+            format_button(mouse_btn).map(|b| format!("mouse_up {} {} {}", b, x, y)),
+
+        Event::MouseWheel { x, y, .. } =>
+            Some(format!("wheel {} {}", x, y)),
+
+        Event::DropFile { ref filename, .. } =>
+            Some(format!("drop {}", filename)),
+
+        Event::FingerDown { finger_id, x, y, .. } =>
+            Some(format!("finger_down {} {} {}", finger_id, x, y)),
+
+        Event::FingerMotion { finger_id, x, y, .. } =>
+            Some(format!("finger_motion {} {} {}", finger_id, x, y)),
+
+        Event::FingerUp { finger_id, x, y, .. } =>
+            Some(format!("finger_up {} {} {}", finger_id, x, y)),
+
+        Event::MultiGesture { num_fingers, d_dist, x, y, .. } =>
+            Some(format!("gesture {} {} {} {}", num_fingers, d_dist, x, y)),
+
+        Event::ControllerButtonDown { button, .. } =>
+            Some(format!("pad_down {}", button.string())),
+
+        Event::ControllerButtonUp { button, .. } =>
+            Some(format!("pad_up {}", button.string())),
+
+        _ => None
+    }
+}
+
+fn parse_event(line: &str) -> Option<TimedEvent> {
+    let mut ws = line.split_whitespace();
+    let ticks = ws.next()?.parse::<u32>().ok()?;
+    let name = ws.next()?;
+
+    let e =
+        match name {
+            "quit" =>
+                Event::Quit { timestamp: ticks },
+
+            "resize" => {
+                let w = next_i32(&mut ws)?;
+                let h = next_i32(&mut ws)?;
+                Event::Window {
+                    timestamp: ticks,
+                    window_id: 0,
+                    win_event: WindowEvent::Resized(w, h) }
+            },
+
+            "focus_lost" =>
+                Event::Window {
+                    timestamp: ticks,
+                    window_id: 0,
+                    win_event: WindowEvent::FocusLost },
+
+            "key_down" | "key_up" => {
+                let keymod = Mod::from_bits_truncate(ws.next()?.parse::<u16>().ok()?);
+                let keycode = Keycode::from_name(&ws.collect::<Vec<&str>>().join(" "))?;
+
+                if name == "key_down" {
+                    Event::KeyDown {
+                        timestamp: ticks, window_id: 0,
+                        keycode: Some(keycode), scancode: None,
+                        keymod: keymod, repeat: false }
+                } else {
+                    Event::KeyUp {
+                        timestamp: ticks, window_id: 0,
+                        keycode: Some(keycode), scancode: None,
+                        keymod: keymod, repeat: false }
+                }
+            },
+
+            "mouse_motion" => {
+                let x = next_i32(&mut ws)?;
+                let y = next_i32(&mut ws)?;
+                let state = ws.next()?.parse::<u32>().ok()?;
+                Event::MouseMotion {
+                    timestamp: ticks, window_id: 0, which: 0,
+                    mousestate: MouseState::from_sdl_state(state),
+                    x: x, y: y, xrel: 0, yrel: 0 }
+            },
+
+            "mouse_down" | "mouse_up" => {
+                let mouse_btn = parse_button(ws.next()?)?;
+                let x = next_i32(&mut ws)?;
+                let y = next_i32(&mut ws)?;
+
+                if name == "mouse_down" {
+                    Event::MouseButtonDown {
+                        timestamp: ticks, window_id: 0, which: 0,
+                        mouse_btn: mouse_btn, x: x, y: y }
+                } else {
+                    Event::MouseButtonUp {
+                        timestamp: ticks, window_id: 0, which: 0,
+                        mouse_btn: mouse_btn, x: x, y: y }
+                }
+            },
+
+            "wheel" => {
+                let x = next_i32(&mut ws)?;
+                let y = next_i32(&mut ws)?;
+                Event::MouseWheel {
+                    timestamp: ticks, window_id: 0, which: 0,
+                    x: x, y: y, direction: MouseWheelDirection::Normal }
+            },
+
+            "drop" => {
+                // the file name is the rest of the line
+                let filename = line.splitn(3, ' ').nth(2)?;
+                Event::DropFile { timestamp: ticks, filename: String::from(filename) }
+            },
+
+            "finger_down" | "finger_motion" | "finger_up" => {
+                let finger_id = ws.next()?.parse::<i64>().ok()?;
+                let x = next_f32(&mut ws)?;
+                let y = next_f32(&mut ws)?;
+
+                match name {
+                    "finger_down" =>
+                        Event::FingerDown {
+                            timestamp: ticks, touch_id: 0, finger_id: finger_id,
+                            x: x, y: y, dx: 0.0, dy: 0.0, pressure: 1.0 },
+                    "finger_motion" =>
+                        Event::FingerMotion {
+                            timestamp: ticks, touch_id: 0, finger_id: finger_id,
+                            x: x, y: y, dx: 0.0, dy: 0.0, pressure: 1.0 },
+                    _ =>
+                        Event::FingerUp {
+                            timestamp: ticks, touch_id: 0, finger_id: finger_id,
+                            x: x, y: y, dx: 0.0, dy: 0.0, pressure: 0.0 }
+                }
+            },
+
+            "gesture" => {
+                let num_fingers = ws.next()?.parse::<u16>().ok()?;
+                let d_dist = next_f32(&mut ws)?;
+                let x = next_f32(&mut ws)?;
+                let y = next_f32(&mut ws)?;
+                Event::MultiGesture {
+                    timestamp: ticks, touch_id: 0, d_theta: 0.0, d_dist: d_dist,
+                    x: x, y: y, num_fingers: num_fingers }
+            },
+
+            "pad_down" => {
+                let button = Button::from_string(ws.next()?)?;
+                Event::ControllerButtonDown { timestamp: ticks, which: 0, button: button }
+            },
+
+            "pad_up" => {
+                let button = Button::from_string(ws.next()?)?;
+                Event::ControllerButtonUp { timestamp: ticks, which: 0, button: button }
+            },
+
+            _ => return None
+        };
+
+    Some((ticks, e))
+}
+
+fn next_i32(ws: &mut SplitWhitespace) -> Option<i32> {
+    ws.next()?.parse::<i32>().ok()
+}
+
+fn next_f32(ws: &mut SplitWhitespace) -> Option<f32> {
+    ws.next()?.parse::<f32>().ok()
+}
+
+fn format_button(button: MouseButton) -> Option<&'static str> {
+    match button {
+        MouseButton::Left => Some("left"),
+        MouseButton::Middle => Some("middle"),
+        MouseButton::Right => Some("right"),
+        MouseButton::X1 => Some("x1"),
+        MouseButton::X2 => Some("x2"),
+        _ => None
+    }
+}
+
+fn parse_button(s: &str) -> Option<MouseButton> {
+    match s {
+        "left" => Some(MouseButton::Left),
+        "middle" => Some(MouseButton::Middle),
+        "right" => Some(MouseButton::Right),
+        "x1" => Some(MouseButton::X1),
+        "x2" => Some(MouseButton::X2),
+        _ => None
+    }
+}
@@ -0,0 +1,183 @@
+// sdl_backend.rs
+
+use sdl2;
+use sdl2::EventPump;
+use sdl2::GameControllerSubsystem;
+use sdl2::Sdl;
+use sdl2::TimerSubsystem;
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::pixels::{Color,PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode,Renderer,Texture};
+use sdl2::video::FullscreenType;
+
+use backend::{Atlas,Backend};
+
+// FIXME - not sure what to import.
+const SDL_WINDOW_FULLSCREEN_DESKTOP: u32 = 0x1001;
+
+pub struct SdlBackend {
+    renderer: Renderer<'static>,
+    texture: Texture,
+    // the atlas again, with linear filtering
+    smooth_texture: Texture,
+    // as given, before recolouring
+    atlas: Atlas,
+    timer: TimerSubsystem,
+    event_pump: EventPump,
+
+    // None if SDL has no controller support
+    controller_subsystem: Option<GameControllerSubsystem>,
+    // controllers stay open to send events
+    controllers: Vec<GameController>,
+
+    // keep SDL initialised while the backend is alive
+    _sdl: Sdl
+}
+
+impl SdlBackend {
+    pub fn new(title: &str, width: u32, height: u32,
+            min_width: u32, min_height: u32, atlas: &Atlas) -> SdlBackend {
+        let sdl = sdl2::init().unwrap();
+        let video = sdl.video().unwrap();
+
+        let mut window
+            = video.window(title, width, height)
+            .resizable()
+            .position_centered()
+            .opengl()
+            .build().unwrap();
+
+        let _ = window.set_minimum_size(min_width, min_height);
+
+        let mut renderer = window.renderer().build().unwrap();
+        // translucent fills are blended, as in the software backend
+        renderer.set_blend_mode(BlendMode::Blend);
+        let timer = sdl.timer().unwrap();
+        let event_pump = sdl.event_pump().unwrap();
+        let controller_subsystem = sdl.game_controller().ok();
+
+        let (texture, smooth_texture) = SdlBackend::create_textures(&renderer, atlas);
+
+        SdlBackend {
+            renderer: renderer,
+            texture: texture,
+            smooth_texture: smooth_texture,
+            atlas: atlas.clone(),
+            timer: timer,
+            event_pump: event_pump,
+            controller_subsystem: controller_subsystem,
+            controllers: Vec::new(),
+            _sdl: sdl
+        }
+    }
+
+    fn create_textures(renderer: &Renderer, atlas: &Atlas) -> (Texture, Texture) {
+        let texture = SdlBackend::create_atlas_texture(renderer, atlas);
+
+        // the filter is picked when the texture is created
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "linear");
+        let smooth_texture = SdlBackend::create_atlas_texture(renderer, atlas);
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+
+        (texture, smooth_texture)
+    }
+
+    fn create_atlas_texture(renderer: &Renderer, atlas: &Atlas) -> Texture {
+        let mut texture = renderer.create_texture_static(
+                PixelFormatEnum::ABGR8888, atlas.width, atlas.height).unwrap();
+        texture.update(None, &atlas.pixels, 4 * atlas.width as usize).unwrap();
+        texture.set_blend_mode(BlendMode::Blend);
+        texture
+    }
+}
+
+impl Backend for SdlBackend {
+    fn size(&self) -> (u32, u32) {
+        self.renderer.output_size().unwrap_or((0, 0))
+    }
+
+    fn draw_sprite(&mut self, src: Rect, dst: Rect) {
+        let _ = self.renderer.copy(&self.texture, Some(src), Some(dst));
+    }
+
+    fn draw_sprite_smooth(&mut self, src: Rect, dst: Rect) {
+        let _ = self.renderer.copy(&self.smooth_texture, Some(src), Some(dst));
+    }
+
+    fn clear(&mut self, colour: Color) {
+        self.renderer.set_draw_color(colour);
+        self.renderer.clear();
+    }
+
+    fn fill_rect(&mut self, colour: Color, rect: Rect) {
+        self.renderer.set_draw_color(colour);
+        let _ = self.renderer.fill_rect(rect);
+    }
+
+    fn draw_rect(&mut self, colour: Color, rect: Rect) {
+        self.renderer.set_draw_color(colour);
+        let _ = self.renderer.draw_rect(rect);
+    }
+
+    fn present(&mut self) {
+        self.renderer.present();
+    }
+
+    fn poll_event(&mut self, timeout: u32) -> Option<Event> {
+        let e = self.event_pump.wait_event_timeout(timeout);
+
+        // controllers plugged in at startup are also added
+        match e {
+            Some(Event::ControllerDeviceAdded { which, .. }) =>
+                if let Some(ref subsystem) = self.controller_subsystem {
+                    match subsystem.open(which as u32) {
+                        Ok(c) => self.controllers.push(c),
+                        Err(e) => println!("Could not open controller: {}", e)
+                    }
+                },
+
+            Some(Event::ControllerDeviceRemoved { which, .. }) =>
+                self.controllers.retain(|c| c.instance_id() != which),
+
+            _ => {}
+        }
+
+        e
+    }
+
+    fn ticks(&mut self) -> u32 {
+        self.timer.ticks()
+    }
+
+    fn recolour_sprites(&mut self, colours: &[(Color, Color)]) {
+        let atlas = self.atlas.recoloured(colours);
+        let (texture, smooth_texture) = SdlBackend::create_textures(&self.renderer, &atlas);
+        self.texture = texture;
+        self.smooth_texture = smooth_texture;
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let window = self.renderer.window_mut().unwrap();
+
+        if window.window_flags() & SDL_WINDOW_FULLSCREEN_DESKTOP != 0 {
+            window.set_fullscreen(FullscreenType::Off).unwrap();
+        } else {
+            window.set_fullscreen(FullscreenType::Desktop).unwrap();
+        }
+    }
+
+    fn is_fullscreen(&self) -> bool {
+        match self.renderer.window() {
+            Some(window) => window.window_flags() & SDL_WINDOW_FULLSCREEN_DESKTOP != 0,
+            None => false
+        }
+    }
+
+    fn set_title(&mut self, title: &str) {
+        if let Some(window) = self.renderer.window_mut() {
+            let _ = window.set_title(title);
+        }
+    }
+}
@@ -0,0 +1,371 @@
+// settings.rs
+//
+// Options kept from one game to the next, in
+// $XDG_CONFIG_HOME/play_picross/settings.txt:
+//
+//   version 1
+//   window_size 640 400
+//   fullscreen false
+//   zoom 1.5
+//   paint filled
+//   auto_cross false
+//   minimap false
+//   theme light
+//   clue_markers false
+//
+// Settings that cannot be read are reported and left at their defaults.
+// Settings from a newer version are kept as they are when saving, along
+// with that version number.
+
+use std::cmp::max;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead,BufReader,BufWriter,Write};
+use std::path::{Path,PathBuf};
+
+use board::Tile;
+use paths;
+use paths::XdgDir;
+use theme::find_theme;
+
+const SETTINGS_FILENAME: &'static str = "settings.txt";
+const SETTINGS_VERSION: u32 = 1;
+const DEFAULT_WINDOW_WIDTH: u32 = 640;
+const DEFAULT_WINDOW_HEIGHT: u32 = 400;
+
+pub struct Settings {
+    pub window_size: (u32, u32),
+    pub fullscreen: bool,
+    pub zoom: f32,
+    pub paint: Tile,
+    pub auto_cross: bool,
+    pub minimap: bool,
+    pub theme: String,
+    pub clue_markers: bool,
+
+    // version of the file read, and the lines this version does not
+    // know about
+    version: u32,
+    unknown: Vec<String>
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings {
+            window_size: (DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT),
+            fullscreen: false,
+            zoom: 1.0,
+            paint: Tile::Filled,
+            auto_cross: false,
+            minimap: false,
+            theme: "light".to_string(),
+            clue_markers: false,
+            version: SETTINGS_VERSION,
+            unknown: Vec::new()
+        }
+    }
+
+    // The saved settings, or the defaults if there are none.
+    pub fn load_user() -> Settings {
+        match settings_path() {
+            Some(path) => Settings::load(&path),
+            None => Settings::new()
+        }
+    }
+
+    fn load(path: &Path) -> Settings {
+        let mut settings = Settings::new();
+        let file = match File::open(path) {
+            Ok(f) => f,
+            Err(_) => return settings
+        };
+
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = match line {
+                Ok(ln) => ln,
+                Err(e) => {
+                    println!("{}: {}", path.display(), e);
+                    break;
+                }
+            };
+
+            let ws: Vec<&str> = line.split_whitespace().collect();
+            if ws.is_empty() || ws[0].starts_with("#") {
+                continue;
+            }
+
+            if ws[0] == "version" {
+                match ws.get(1).and_then(|v| v.parse::<u32>().ok()) {
+                    Some(v) => settings.version = v,
+                    None => println!("{} line {}: bad version", path.display(), i + 1)
+                }
+                continue;
+            }
+
+            match settings.read_setting(&ws) {
+                Ok(true) => {},
+                Ok(false) => {
+                    if settings.version <= SETTINGS_VERSION {
+                        println!("{} line {}: unknown setting '{}'",
+                                path.display(), i + 1, ws[0]);
+                    }
+                    settings.unknown.push(line.clone());
+                },
+                Err(msg) =>
+                    println!("{} line {}: {}", path.display(), i + 1, msg)
+            }
+        }
+
+        settings
+    }
+
+    pub fn save_user(&self) -> io::Result<()> {
+        let path = settings_path().ok_or(
+                io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+        self.save(&path)
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Written beside the settings and moved over them, so that a failed
+        // write leaves the old settings in place.
+        let tmp_path = path.with_extension("txt.tmp");
+        let file = File::create(&tmp_path)?;
+        let written = {
+            let mut f = BufWriter::new(&file);
+            self.write_to(&mut f).and_then(|_| f.flush())
+        };
+
+        if let Err(e) = written.and_then(|_| file.sync_all()) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e)
+        }
+
+        fs::rename(&tmp_path, path)
+    }
+
+    fn write_to<W: Write>(&self, f: &mut W) -> io::Result<()> {
+        let (w, h) = self.window_size;
+
+        writeln!(f, "# play_picross settings")?;
+        writeln!(f, "version {}", max(self.version, SETTINGS_VERSION))?;
+        writeln!(f, "window_size {} {}", w, h)?;
+        writeln!(f, "fullscreen {}", self.fullscreen)?;
+        writeln!(f, "zoom {}", self.zoom)?;
+        writeln!(f, "paint {}", tile_name(self.paint))?;
+        writeln!(f, "auto_cross {}", self.auto_cross)?;
+        writeln!(f, "minimap {}", self.minimap)?;
+        writeln!(f, "theme {}", self.theme)?;
+        writeln!(f, "clue_markers {}", self.clue_markers)?;
+
+        for line in self.unknown.iter() {
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    // Ok(false) if the setting is not known.
+    fn read_setting(&mut self, ws: &[&str]) -> Result<bool, String> {
+        let bad_value = || format!("bad value for '{}'", ws[0]);
+        let value = |i: usize| ws.get(i).copied().ok_or_else(bad_value);
+
+        match ws[0] {
+            "window_size" => {
+                let w = value(1)?.parse::<u32>().map_err(|_| bad_value())?;
+                let h = value(2)?.parse::<u32>().map_err(|_| bad_value())?;
+                if w == 0 || h == 0 {
+                    return Err(bad_value())
+                }
+                self.window_size = (w, h);
+            },
+
+            "fullscreen" =>
+                self.fullscreen = value(1)?.parse::<bool>().map_err(|_| bad_value())?,
+
+            "zoom" => {
+                let z = value(1)?.parse::<f32>().map_err(|_| bad_value())?;
+                if !z.is_finite() {
+                    return Err(bad_value())
+                }
+                self.zoom = z;
+            },
+
+            "paint" =>
+                self.paint = match value(1)? {
+                    "empty" => Tile::Empty,
+                    "crossed_out" => Tile::CrossedOut,
+                    "filled" => Tile::Filled,
+                    _ => return Err(bad_value())
+                },
+
+            "auto_cross" =>
+                self.auto_cross = value(1)?.parse::<bool>().map_err(|_| bad_value())?,
+
+            "minimap" =>
+                self.minimap = value(1)?.parse::<bool>().map_err(|_| bad_value())?,
+
+            "theme" => {
+                let name = value(1)?;
+                if find_theme(name).is_none() {
+                    return Err(format!("unknown theme '{}'", name))
+                }
+                self.theme = name.to_string();
+            },
+
+            "clue_markers" =>
+                self.clue_markers = value(1)?.parse::<bool>().map_err(|_| bad_value())?,
+
+            _ => return Ok(false)
+        }
+
+        Ok(true)
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new()
+    }
+}
+
+fn tile_name(tile: Tile) -> &'static str {
+    match tile {
+        Tile::Empty => "empty",
+        Tile::CrossedOut => "crossed_out",
+        Tile::Filled => "filled"
+    }
+}
+
+// $XDG_CONFIG_HOME/play_picross/settings.txt
+fn settings_path() -> Option<PathBuf> {
+    paths::xdg_file(XdgDir::Config, SETTINGS_FILENAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+
+    // A settings file of its own for each test, under the temp directory.
+    fn temp_path(name: &str) -> PathBuf {
+        let mut path = env::temp_dir();
+        path.push(format!("play_picross-test-{}", process::id()));
+        path.push(name);
+        path
+    }
+
+    fn load_str(name: &str, contents: &str) -> Settings {
+        let path = temp_path(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+
+        let settings = Settings::load(&path);
+        fs::remove_file(&path).unwrap();
+        settings
+    }
+
+    fn saved(settings: &Settings) -> String {
+        let mut out = Vec::new();
+        settings.write_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn read_setting_values() {
+        let mut s = Settings::new();
+
+        assert_eq!(s.read_setting(&["window_size", "800", "600"]), Ok(true));
+        assert_eq!(s.read_setting(&["paint", "crossed_out"]), Ok(true));
+        assert_eq!(s.read_setting(&["minimap", "true"]), Ok(true));
+        assert_eq!(s.read_setting(&["zoom", "2"]), Ok(true));
+        assert!(s.window_size == (800, 600));
+        assert!(s.paint == Tile::CrossedOut);
+        assert!(s.minimap);
+        assert!(s.zoom == 2.0);
+
+        assert_eq!(s.read_setting(&["sound", "on"]), Ok(false));
+    }
+
+    #[test]
+    fn read_setting_rejects_bad_values() {
+        let mut s = Settings::new();
+
+        assert!(s.read_setting(&["window_size", "800"]).is_err());
+        assert!(s.read_setting(&["window_size", "0", "600"]).is_err());
+        assert!(s.read_setting(&["zoom", "inf"]).is_err());
+        assert!(s.read_setting(&["fullscreen", "yes"]).is_err());
+        assert!(s.read_setting(&["paint", "blue"]).is_err());
+        assert!(s.read_setting(&["theme", "no_such_theme"]).is_err());
+
+        // nothing was changed
+        assert!(s.window_size == (DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT));
+        assert!(s.zoom == 1.0 && !s.fullscreen && s.paint == Tile::Filled);
+    }
+
+    #[test]
+    fn load_skips_malformed_lines() {
+        let s = load_str("malformed.txt", "\
+version 1
+window_size 800
+zoom 1.5
+minimap maybe
+auto_cross true
+");
+        assert!(s.window_size == (DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT));
+        assert!(s.zoom == 1.5);
+        assert!(!s.minimap);
+        assert!(s.auto_cross);
+    }
+
+    #[test]
+    fn load_old_version() {
+        // written before the version line and the later settings
+        let s = load_str("old.txt", "window_size 800 600\nfullscreen true\n");
+        assert!(s.window_size == (800, 600));
+        assert!(s.fullscreen);
+        assert!(s.theme == "light" && !s.clue_markers);
+        assert!(saved(&s).contains(&format!("version {}\n", SETTINGS_VERSION)));
+    }
+
+    #[test]
+    fn unknown_lines_are_kept() {
+        let s = load_str("newer.txt", "\
+version 9
+zoom 2
+sound on
+");
+        assert!(s.zoom == 2.0);
+
+        let out = saved(&s);
+        assert!(out.contains("version 9\n"));
+        assert!(out.contains("\nsound on\n"));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut s = Settings::new();
+        s.window_size = (1024, 768);
+        s.fullscreen = true;
+        s.zoom = 1.25;
+        s.paint = Tile::Empty;
+        s.auto_cross = true;
+        s.minimap = true;
+        s.clue_markers = true;
+        s.unknown.push("sound on".to_string());
+
+        let path = temp_path("round_trip.txt");
+        s.save(&path).unwrap();
+        assert!(!path.with_extension("txt.tmp").exists());
+
+        let loaded = Settings::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(saved(&loaded) == saved(&s));
+    }
+}
@@ -0,0 +1,265 @@
+// soft_backend.rs
+//
+// Backend that draws into memory and takes its input from a queue,
+// so the GUI can run without a display.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter,Write};
+use std::path::Path;
+use std::rc::Rc;
+use sdl2::event::{Event,WindowEvent};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use backend::{Atlas,Backend};
+
+// Cloning gives another handle to the same screen, so that a test can
+// keep feeding events and reading pixels after handing a clone to the Gui.
+#[derive(Clone)]
+pub struct SoftwareBackend {
+    screen: Rc<RefCell<Screen>>
+}
+
+struct Screen {
+    width: u32,
+    height: u32,
+
+    // 4 bytes per pixel in R, G, B, A order
+    pixels: Vec<u8>,
+    atlas: Option<Atlas>,
+    // as given, before recolouring
+    original_atlas: Option<Atlas>,
+    title: String,
+
+    // (ticks when due, event)
+    events: VecDeque<(u32, Event)>,
+    ticks: u32,
+    frames: u32
+}
+
+impl SoftwareBackend {
+    // Without an atlas, sprites are not drawn.
+    pub fn new(width: u32, height: u32, atlas: Option<Atlas>) -> SoftwareBackend {
+        let screen = Screen {
+            width: width,
+            height: height,
+            pixels: vec![0; 4 * (width * height) as usize],
+            atlas: atlas.clone(),
+            original_atlas: atlas,
+            title: String::new(),
+            events: VecDeque::new(),
+            ticks: 0,
+            frames: 0
+        };
+
+        SoftwareBackend { screen: Rc::new(RefCell::new(screen)) }
+    }
+
+    pub fn push_event(&self, event: Event) {
+        self.push_event_at(0, event);
+    }
+
+    // Queue an event that is not delivered before the given time.
+    pub fn push_event_at(&self, ticks: u32, event: Event) {
+        self.screen.borrow_mut().events.push_back((ticks, event));
+    }
+
+    pub fn has_events(&self) -> bool {
+        !self.screen.borrow().events.is_empty()
+    }
+
+    // Resize the screen once the Gui reads the event.
+    pub fn resize(&self, width: u32, height: u32) {
+        self.push_event(Event::Window {
+                timestamp: 0,
+                window_id: 0,
+                win_event: WindowEvent::Resized(width as i32, height as i32) });
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        let s = self.screen.borrow();
+        if x < s.width && y < s.height {
+            let i = 4 * (s.width * y + x) as usize;
+            Some(Color::RGB(s.pixels[i], s.pixels[i + 1], s.pixels[i + 2]))
+        } else {
+            None
+        }
+    }
+
+    // Copy of the screen, 4 bytes per pixel in R, G, B, A order.
+    pub fn pixels(&self) -> Vec<u8> {
+        self.screen.borrow().pixels.clone()
+    }
+
+    // Save the screen as a binary PPM image.
+    pub fn save_ppm(&self, path: &Path) -> io::Result<()> {
+        let s = self.screen.borrow();
+        let mut out = BufWriter::new(File::create(path)?);
+
+        write!(out, "P6\n{} {}\n255\n", s.width, s.height)?;
+        for p in s.pixels.chunks(4) {
+            out.write_all(&p[0..3])?;
+        }
+        out.flush()
+    }
+
+    pub fn title(&self) -> String {
+        self.screen.borrow().title.clone()
+    }
+
+    // Ticks passed so far.
+    pub fn now(&self) -> u32 {
+        self.screen.borrow().ticks
+    }
+
+    // Number of frames presented so far.
+    pub fn frames(&self) -> u32 {
+        self.screen.borrow().frames
+    }
+}
+
+impl Screen {
+    // Intersection of the rect with the screen, as (x1, y1, x2, y2).
+    fn clip(&self, rect: Rect) -> Option<(u32, u32, u32, u32)> {
+        let x1 = rect.x().max(0);
+        let y1 = rect.y().max(0);
+        let x2 = (rect.x() + rect.width() as i32).min(self.width as i32);
+        let y2 = (rect.y() + rect.height() as i32).min(self.height as i32);
+
+        if x1 < x2 && y1 < y2 {
+            Some((x1 as u32, y1 as u32, x2 as u32, y2 as u32))
+        } else {
+            None
+        }
+    }
+
+    fn blend(&mut self, x: u32, y: u32, r: u8, g: u8, b: u8, a: u8) {
+        let i = 4 * (self.width * y + x) as usize;
+        let mix = |dst: u8, src: u8|
+            ((src as u32 * a as u32 + dst as u32 * (255 - a as u32)) / 255) as u8;
+
+        self.pixels[i] = mix(self.pixels[i], r);
+        self.pixels[i + 1] = mix(self.pixels[i + 1], g);
+        self.pixels[i + 2] = mix(self.pixels[i + 2], b);
+        self.pixels[i + 3] = 255;
+    }
+
+    fn fill(&mut self, colour: Color, rect: Rect) {
+        let (r, g, b, a) = colour.rgba();
+
+        if let Some((x1, y1, x2, y2)) = self.clip(rect) {
+            for y in y1..y2 {
+                for x in x1..x2 {
+                    self.blend(x, y, r, g, b, a);
+                }
+            }
+        }
+    }
+}
+
+impl Backend for SoftwareBackend {
+    fn size(&self) -> (u32, u32) {
+        let s = self.screen.borrow();
+        (s.width, s.height)
+    }
+
+    fn draw_sprite(&mut self, src: Rect, dst: Rect) {
+        let mut s = self.screen.borrow_mut();
+        let atlas = match s.atlas.take() {
+            Some(a) => a,
+            None => return
+        };
+
+        if let Some((x1, y1, x2, y2)) = s.clip(dst) {
+            for y in y1..y2 {
+                for x in x1..x2 {
+                    // nearest neighbour
+                    let sx = src.x() as u32
+                        + (x as i32 - dst.x()) as u32 * src.width() / dst.width();
+                    let sy = src.y() as u32
+                        + (y as i32 - dst.y()) as u32 * src.height() / dst.height();
+                    if sx >= atlas.width || sy >= atlas.height {
+                        continue;
+                    }
+
+                    let i = 4 * (atlas.width * sy + sx) as usize;
+                    let p = &atlas.pixels[i .. i + 4];
+                    s.blend(x, y, p[0], p[1], p[2], p[3]);
+                }
+            }
+        }
+
+        s.atlas = Some(atlas);
+    }
+
+    fn clear(&mut self, colour: Color) {
+        let mut s = self.screen.borrow_mut();
+        let (w, h) = (s.width, s.height);
+        let (r, g, b, _) = colour.rgba();
+
+        s.fill(Color::RGB(r, g, b), Rect::new(0, 0, w, h));
+    }
+
+    fn fill_rect(&mut self, colour: Color, rect: Rect) {
+        self.screen.borrow_mut().fill(colour, rect);
+    }
+
+    fn draw_rect(&mut self, colour: Color, rect: Rect) {
+        let mut s = self.screen.borrow_mut();
+        let (x, y, w, h) = (rect.x(), rect.y(), rect.width(), rect.height());
+
+        s.fill(colour, Rect::new(x, y, w, 1));
+        s.fill(colour, Rect::new(x, y + h as i32 - 1, w, 1));
+        s.fill(colour, Rect::new(x, y, 1, h));
+        s.fill(colour, Rect::new(x + w as i32 - 1, y, 1, h));
+    }
+
+    fn present(&mut self) {
+        self.screen.borrow_mut().frames += 1;
+    }
+
+    // Time only passes while waiting for events, so runs are repeatable.
+    fn poll_event(&mut self, timeout: u32) -> Option<Event> {
+        let mut s = self.screen.borrow_mut();
+        let due = match s.events.front() {
+            Some(&(t, _)) if t <= s.ticks + timeout => t,
+            _ => {
+                s.ticks = s.ticks + timeout;
+                return None
+            }
+        };
+
+        s.ticks = s.ticks.max(due);
+        let e = s.events.pop_front().map(|(_, e)| e);
+
+        if let Some(Event::Window { win_event: WindowEvent::Resized(w, h), .. }) = e {
+            s.width = w as u32;
+            s.height = h as u32;
+            s.pixels = vec![0; 4 * (s.width * s.height) as usize];
+        }
+        e
+    }
+
+    fn ticks(&mut self) -> u32 {
+        self.screen.borrow().ticks
+    }
+
+    fn recolour_sprites(&mut self, colours: &[(Color, Color)]) {
+        let mut s = self.screen.borrow_mut();
+        s.atlas = s.original_atlas.as_ref().map(|a| a.recoloured(colours));
+    }
+
+    fn toggle_fullscreen(&mut self) {
+    }
+
+    fn is_fullscreen(&self) -> bool {
+        false
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.screen.borrow_mut().title = String::from(title);
+    }
+}
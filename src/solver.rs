@@ -0,0 +1,224 @@
+// solver.rs
+
+use board::Board;
+use board::Deduction;
+use board::Tile;
+use puzzle::Rule;
+use puzzle::Rules;
+
+pub enum Solution {
+    NoSolution,
+    Unique(Board),
+
+    // Multiple(first, second) - two of the possible solutions
+    Multiple(Board, Board)
+}
+
+pub fn solve(width: usize, height: usize, rules: Rules) -> Solution {
+    let mut found = Vec::new();
+
+    search(Board::new(width, height), rules, &mut found);
+
+    match found.len() {
+        0 => Solution::NoSolution,
+        1 => Solution::Unique(found.pop().unwrap()),
+        _ => {
+            let second = found.pop().unwrap();
+            let first = found.pop().unwrap();
+            Solution::Multiple(first, second)
+        }
+    }
+}
+
+// Apply line logic until nothing more can be deduced.
+// Returns None if the board contradicts the rules.
+pub fn propagate(board: Board, rules: Rules) -> Option<Board> {
+    let mut b = board;
+
+    loop {
+        match b.deduce(rules) {
+            Deduction::Progress(new_b) => b = new_b,
+            Deduction::Stuck => return Some(b),
+            Deduction::Conflict => return None
+        }
+    }
+}
+
+// Solve using line logic alone, without guessing.
+// Returns the board it got stuck on (complete if the puzzle was solved)
+// and the number of rounds of deduction needed, or None on a conflict.
+pub fn line_solve(width: usize, height: usize, rules: Rules)
+        -> Option<(Board, u32)> {
+    let mut b = Board::new(width, height);
+    let mut passes = 0;
+
+    loop {
+        match b.deduce(rules) {
+            Deduction::Progress(new_b) => {
+                b = new_b;
+                passes += 1;
+            },
+            Deduction::Stuck => return Some((b, passes)),
+            Deduction::Conflict => return None
+        }
+    }
+}
+
+// Range of tiles, as (first, last), that block number index of the rule
+// could cover in some arrangement of the rule that fits the line.
+// Returns None if the rule does not fit the line at all.
+pub fn block_range(line: &[Tile], rule: &Rule, index: usize) -> Option<(usize, usize)> {
+    let n = line.len();
+    let k = rule.len();
+    if index >= k {
+        return None
+    }
+
+    let no_tile = |t: Tile, from: usize, to: usize| line[from..to].iter().all(|&x| x != t);
+
+    // before[i][j]: the first i blocks fit in tiles 0..j
+    let mut before = vec![vec![false; n + 1]; k + 1];
+    for (j, fits) in before[0].iter_mut().enumerate() {
+        *fits = no_tile(Tile::Filled, 0, j);
+    }
+    for i in 1..k + 1 {
+        let len = rule[i - 1] as usize;
+        for j in 1..n + 1 {
+            let gap = before[i][j - 1] && line[j - 1] != Tile::Filled;
+            let block = j >= len && no_tile(Tile::CrossedOut, j - len, j)
+                && if j == len {
+                    i == 1
+                } else {
+                    line[j - len - 1] != Tile::Filled && before[i - 1][j - len - 1]
+                };
+            before[i][j] = gap || block;
+        }
+    }
+
+    // after[i][j]: blocks i.. fit in tiles j..n
+    let mut after = vec![vec![false; n + 1]; k + 1];
+    for (j, fits) in after[k].iter_mut().enumerate() {
+        *fits = no_tile(Tile::Filled, j, n);
+    }
+    for i in (0..k).rev() {
+        let len = rule[i] as usize;
+        for j in (0..n).rev() {
+            let gap = after[i][j + 1] && line[j] != Tile::Filled;
+            let block = j + len <= n && no_tile(Tile::CrossedOut, j, j + len)
+                && if j + len == n {
+                    i + 1 == k
+                } else {
+                    line[j + len] != Tile::Filled && after[i + 1][j + len + 1]
+                };
+            after[i][j] = gap || block;
+        }
+    }
+
+    // try each start of the block
+    let len = rule[index] as usize;
+    let mut range: Option<(usize, usize)> = None;
+    for start in 0..(n + 1).saturating_sub(len) {
+        let end = start + len;
+        let fits = no_tile(Tile::CrossedOut, start, end)
+            && (if start == 0 {
+                    index == 0
+                } else {
+                    line[start - 1] != Tile::Filled && before[index][start - 1]
+                })
+            && (if end == n {
+                    index + 1 == k
+                } else {
+                    line[end] != Tile::Filled && after[index + 1][end + 1]
+                });
+
+        if fits {
+            range = Some(match range {
+                Some((first, _)) => (first, end - 1),
+                None => (start, end - 1)
+            });
+        }
+    }
+
+    range
+}
+
+// Look for up to two solutions, guessing when line logic gets stuck.
+fn search(board: Board, rules: Rules, found: &mut Vec<Board>) {
+    let b = match propagate(board, rules) {
+        Some(b) => b,
+        None => return
+    };
+
+    match b.find_empty() {
+        None =>
+            if b.is_solved(rules) {
+                found.push(b);
+            },
+
+        Some((x, y)) =>
+            for &t in [Tile::Filled, Tile::CrossedOut].iter() {
+                if found.len() >= 2 {
+                    return;
+                }
+
+                let mut guess = b.clone();
+                guess.set(x, y, t);
+                search(guess, rules, found);
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_filled(b: &Board, x: u32, y: u32) -> bool {
+        b.get(x, y) == Some(Tile::Filled)
+    }
+
+    #[test]
+    fn unique_solution() {
+        // an L in the top left corner
+        let rows = vec![vec![2], vec![1]];
+        let cols = vec![vec![2], vec![1]];
+
+        match solve(2, 2, (&rows, &cols)) {
+            Solution::Unique(b) => {
+                assert!(is_filled(&b, 0, 0));
+                assert!(is_filled(&b, 1, 0));
+                assert!(is_filled(&b, 0, 1));
+                assert!(!is_filled(&b, 1, 1));
+            },
+            _ => panic!("expected a unique solution")
+        }
+    }
+
+    #[test]
+    fn multiple_solutions() {
+        // either diagonal fits
+        let rows = vec![vec![1], vec![1]];
+        let cols = vec![vec![1], vec![1]];
+
+        match solve(2, 2, (&rows, &cols)) {
+            Solution::Multiple(a, b) => {
+                assert!(a != b);
+                assert!(is_filled(&a, 0, 0) != is_filled(&a, 1, 0));
+                assert!(is_filled(&b, 0, 0) != is_filled(&b, 1, 0));
+            },
+            _ => panic!("expected multiple solutions")
+        }
+    }
+
+    #[test]
+    fn no_solution() {
+        // the rows fill four tiles, the columns two
+        let rows = vec![vec![2], vec![2]];
+        let cols = vec![vec![1], vec![1]];
+
+        match solve(2, 2, (&rows, &cols)) {
+            Solution::NoSolution => {},
+            _ => panic!("expected no solution")
+        }
+        assert!(line_solve(2, 2, (&rows, &cols)).is_none());
+    }
+}
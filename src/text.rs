@@ -0,0 +1,290 @@
+// text.rs
+//
+// A 5x7 font for printable ASCII, for words rather than clues.  The
+// glyphs are drawn into a sheet below the sprites when the atlas is
+// loaded, in the atlas black, so they scale and follow the theme like
+// the other sprites.
+
+use sdl2::rect::Rect;
+
+use backend::Atlas;
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+// distance from one glyph, or line, to the next
+pub const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 1;
+pub const LINE_ADVANCE: u32 = GLYPH_HEIGHT + 3;
+
+pub const FIRST_GLYPH: u8 = b' ';
+pub const LAST_GLYPH: u8 = b'~';
+
+// below the 100x100 sprite sheet, whose sprites are at fixed places
+const SHEET_Y: u32 = 100;
+const GLYPHS_PER_ROW: u32 = 16;
+
+// One byte per row, with the leftmost pixel in bit 4.
+const GLYPHS: [[u8; 7]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04], // !
+    [0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00], // "
+    [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A], // #
+    [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04], // $
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // %
+    [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D], // &
+    [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00], // '
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // (
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // )
+    [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00], // *
+    [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08], // ,
+    [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C], // .
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // /
+    [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E], // 0
+    [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E], // 1
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F], // 2
+    [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E], // 3
+    [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02], // 4
+    [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E], // 5
+    [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E], // 6
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // 7
+    [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E], // 8
+    [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C], // 9
+    [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00], // :
+    [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08], // ;
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // <
+    [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00], // =
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // >
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // ?
+    [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E], // @
+    [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11], // A
+    [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E], // B
+    [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E], // C
+    [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C], // D
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F], // E
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10], // F
+    [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F], // G
+    [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // H
+    [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E], // I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C], // J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F], // L
+    [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11], // M
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // N
+    [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // O
+    [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10], // P
+    [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D], // Q
+    [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11], // R
+    [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E], // S
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04], // V
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A], // W
+    [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11], // X
+    [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04], // Y
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F], // Z
+    [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E], // [
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // backslash
+    [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E], // ]
+    [0x04, 0x0A, 0x11, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F], // _
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00], // `
+    [0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F], // a
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E], // b
+    [0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E], // c
+    [0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F], // d
+    [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E], // e
+    [0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08], // f
+    [0x00, 0x0F, 0x11, 0x11, 0x0F, 0x01, 0x0E], // g
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11], // h
+    [0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E], // i
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0C], // j
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12], // k
+    [0x0C, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E], // l
+    [0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11], // m
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11], // n
+    [0x00, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E], // o
+    [0x00, 0x00, 0x1E, 0x11, 0x1E, 0x10, 0x10], // p
+    [0x00, 0x00, 0x0D, 0x13, 0x0F, 0x01, 0x01], // q
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10], // r
+    [0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E], // s
+    [0x08, 0x08, 0x1C, 0x08, 0x08, 0x09, 0x06], // t
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0D], // u
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0A, 0x04], // v
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0A], // w
+    [0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11], // x
+    [0x00, 0x00, 0x11, 0x11, 0x0F, 0x01, 0x0E], // y
+    [0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F], // z
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02], // {
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // |
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08], // }
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00]  // ~
+];
+
+// Where the glyph for c is in the atlas.
+pub fn glyph_rect(c: u8) -> Rect {
+    let i = (c - FIRST_GLYPH) as u32;
+    Rect::new(
+            (GLYPH_ADVANCE * (i % GLYPHS_PER_ROW)) as i32,
+            (SHEET_Y + LINE_ADVANCE * (i / GLYPHS_PER_ROW)) as i32,
+            GLYPH_WIDTH, GLYPH_HEIGHT)
+}
+
+// The atlas, made taller to fit the glyphs below the sprites.
+// Panics if the sprites reach down into the glyphs.
+pub fn add_glyph_sheet(atlas: Atlas) -> Atlas {
+    assert!(atlas.height <= SHEET_Y,
+            "sprite sheet is {} pixels tall, the glyphs start at {}", atlas.height, SHEET_Y);
+
+    let rows = (GLYPHS.len() as u32).div_ceil(GLYPHS_PER_ROW);
+    let width = atlas.width.max(GLYPH_ADVANCE * GLYPHS_PER_ROW);
+    let height = SHEET_Y + LINE_ADVANCE * rows;
+
+    let mut pixels = vec![0; 4 * (width * height) as usize];
+    for y in 0..atlas.height as usize {
+        let src = 4 * atlas.width as usize * y;
+        let dst = 4 * width as usize * y;
+        pixels[dst .. dst + 4 * atlas.width as usize]
+            .copy_from_slice(&atlas.pixels[src .. src + 4 * atlas.width as usize]);
+    }
+
+    for (i, glyph) in GLYPHS.iter().enumerate() {
+        let rect = glyph_rect(FIRST_GLYPH + i as u8);
+
+        for (gy, &bits) in glyph.iter().enumerate() {
+            for gx in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> gx) == 0 {
+                    continue;
+                }
+
+                let x = rect.x() as usize + gx as usize;
+                let y = rect.y() as usize + gy;
+                let p = 4 * (width as usize * y + x);
+
+                // black, opaque
+                pixels[p] = 0x00;
+                pixels[p + 1] = 0x00;
+                pixels[p + 2] = 0x00;
+                pixels[p + 3] = 0xFF;
+            }
+        }
+    }
+
+    Atlas { width, height, pixels }
+}
+
+// Characters without a glyph are drawn as '?'.
+pub fn glyph_for(c: char) -> u8 {
+    if c >= FIRST_GLYPH as char && c <= LAST_GLYPH as char {
+        c as u8
+    } else {
+        b'?'
+    }
+}
+
+pub fn text_width(s: &str, scale: u32) -> u32 {
+    let n = s.chars().count() as u32;
+    if n == 0 {
+        0
+    } else {
+        scale * (GLYPH_ADVANCE * n - 1)
+    }
+}
+
+// Break s into lines of at most max_chars, between words where possible.
+pub fn wrap_text(s: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in s.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+
+        if !line.is_empty() && line.chars().count() + 1 + word.len() > max_chars {
+            lines.push(line);
+            line = String::new();
+        }
+
+        // words too long for a line are split, and the line is empty here
+        while word.len() > max_chars {
+            let rest = word.split_off(max_chars);
+            lines.push(word.into_iter().collect());
+            word = rest;
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atlas(width: u32, height: u32) -> Atlas {
+        // opaque white
+        Atlas { width, height, pixels: vec![0xFF; 4 * (width * height) as usize] }
+    }
+
+    #[test]
+    fn wrap_between_words() {
+        assert!(wrap_text("one two three", 7) == vec!["one two", "three"]);
+        assert!(wrap_text("  one   two  ", 20) == vec!["one two"]);
+    }
+
+    #[test]
+    fn wrap_exact_width() {
+        assert!(wrap_text("abc def", 7) == vec!["abc def"]);
+        assert!(wrap_text("abc def", 6) == vec!["abc", "def"]);
+        assert!(wrap_text("abcdef", 6) == vec!["abcdef"]);
+    }
+
+    #[test]
+    fn wrap_long_word() {
+        assert!(wrap_text("abcdefghij", 4) == vec!["abcd", "efgh", "ij"]);
+        assert!(wrap_text("to abcdefghij", 4) == vec!["to", "abcd", "efgh", "ij"]);
+        assert!(wrap_text("abcdefgh ij", 4) == vec!["abcd", "efgh", "ij"]);
+        assert!(wrap_text("abc", 0) == vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn wrap_empty() {
+        assert!(wrap_text("", 10).is_empty());
+        assert!(wrap_text(" \n ", 10).is_empty());
+    }
+
+    #[test]
+    fn glyph_sheet_below_sprites() {
+        let a = add_glyph_sheet(atlas(100, 100));
+        let pixel = |x: i32, y: i32| {
+            let p = 4 * (a.width as usize * y as usize + x as usize);
+            &a.pixels[p .. p + 4]
+        };
+
+        // the sprites are kept
+        assert!(pixel(0, 0) == [0xFF; 4]);
+        assert!(pixel(99, 99) == [0xFF; 4]);
+
+        // the top of the 'T' is drawn, and the glyphs fit
+        let t = glyph_rect(b'T');
+        assert!(t.y() >= SHEET_Y as i32);
+        assert!(pixel(t.x(), t.y()) == [0x00, 0x00, 0x00, 0xFF]);
+
+        let last = glyph_rect(LAST_GLYPH);
+        assert!(last.right() as u32 <= a.width && last.bottom() as u32 <= a.height);
+    }
+
+    #[test]
+    #[should_panic]
+    fn glyph_sheet_needs_room() {
+        add_glyph_sheet(atlas(100, 120));
+    }
+}
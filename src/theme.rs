@@ -0,0 +1,106 @@
+// theme.rs
+//
+// Colours of everything drawn, including the sprites.  The atlas is drawn
+// in four colours, which each theme replaces.
+
+use sdl2::pixels::Color;
+
+pub struct Theme {
+    pub name: &'static str,
+
+    pub background: Color,
+    pub grid: Color,
+    // every fifth line and the edges of the board
+    pub grid_major: Color,
+    // row and column under the mouse, hovered clues
+    pub highlight: Color,
+    pub toolbar: Color,
+    // borders, dividers and strike-throughs
+    pub foreground: Color,
+    // keyboard cursor, selected tool, minimap view
+    pub active: Color,
+    // behind clues pinned to the edges of the screen
+    pub clue_shade: Color,
+    // where the block of a hovered clue could go
+    pub block_range: Color,
+
+    // the atlas colours in ATLAS_COLOURS order
+    pub sprites: [Color; 4]
+}
+
+// black, dark grey, light grey and red
+pub const ATLAS_COLOURS: [Color; 4] = [
+    Color::RGB(0x00, 0x00, 0x00),
+    Color::RGB(0x58, 0x58, 0x58),
+    Color::RGB(0xD0, 0xD0, 0xD0),
+    Color::RGB(0xFC, 0x00, 0x00)
+];
+
+pub static THEMES: [Theme; 3] = [
+    Theme {
+        name: "light",
+        background: Color::RGB(0xD0, 0xD0, 0xD0),
+        grid: Color::RGB(0x98, 0x98, 0x98),
+        grid_major: Color::RGB(0x58, 0x58, 0x58),
+        highlight: Color::RGB(0xC2, 0xBC, 0xBC),
+        toolbar: Color::RGB(0x98, 0x98, 0x98),
+        foreground: Color::RGB(0x58, 0x58, 0x58),
+        active: Color::RGB(0xC0, 0x20, 0x20),
+        clue_shade: Color::RGB(0xC4, 0xC4, 0xC4),
+        block_range: Color::RGBA(0x40, 0x80, 0xC0, 0x60),
+        sprites: ATLAS_COLOURS
+    },
+
+    Theme {
+        name: "dark",
+        background: Color::RGB(0x20, 0x20, 0x24),
+        grid: Color::RGB(0x40, 0x40, 0x46),
+        grid_major: Color::RGB(0x88, 0x88, 0x90),
+        highlight: Color::RGB(0x3C, 0x34, 0x38),
+        toolbar: Color::RGB(0x38, 0x38, 0x3E),
+        foreground: Color::RGB(0xA0, 0xA0, 0xA8),
+        active: Color::RGB(0xE0, 0x50, 0x50),
+        clue_shade: Color::RGB(0x2C, 0x2C, 0x32),
+        block_range: Color::RGBA(0x50, 0x90, 0xE0, 0x60),
+        sprites: [
+            Color::RGB(0xE0, 0xE0, 0xE8),
+            Color::RGB(0xA0, 0xA0, 0xA8),
+            Color::RGB(0x30, 0x30, 0x36),
+            Color::RGB(0xFF, 0x60, 0x60)
+        ]
+    },
+
+    Theme {
+        name: "high_contrast",
+        background: Color::RGB(0xFF, 0xFF, 0xFF),
+        grid: Color::RGB(0x80, 0x80, 0x80),
+        grid_major: Color::RGB(0x00, 0x00, 0x00),
+        highlight: Color::RGB(0xFF, 0xE0, 0x60),
+        toolbar: Color::RGB(0xC0, 0xC0, 0xC0),
+        foreground: Color::RGB(0x00, 0x00, 0x00),
+        active: Color::RGB(0x00, 0x00, 0xFF),
+        clue_shade: Color::RGB(0xE8, 0xE8, 0xE8),
+        block_range: Color::RGBA(0x00, 0x60, 0xFF, 0x70),
+        sprites: [
+            Color::RGB(0x00, 0x00, 0x00),
+            Color::RGB(0x30, 0x30, 0x30),
+            Color::RGB(0xFF, 0xFF, 0xFF),
+            Color::RGB(0xE0, 0x00, 0x00)
+        ]
+    }
+];
+
+pub fn find_theme(name: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|t| t.name == name)
+}
+
+// The theme after this one, going back to the first.
+pub fn next_theme(theme: &Theme) -> &'static Theme {
+    let i = THEMES.iter().position(|t| t.name == theme.name).unwrap_or(0);
+    &THEMES[(i + 1) % THEMES.len()]
+}
+
+// (atlas colour, theme colour) for Backend::recolour_sprites.
+pub fn sprite_colours(theme: &Theme) -> Vec<(Color, Color)> {
+    ATLAS_COLOURS.iter().cloned().zip(theme.sprites.iter().cloned()).collect()
+}
@@ -0,0 +1,152 @@
+// backend.rs
+//
+// The drawing rules every backend must follow, checked on the software
+// backend that the other tests draw with.
+
+#![cfg(feature = "gui")]
+
+extern crate play_picross;
+extern crate sdl2;
+
+use std::cmp::min;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+use play_picross::backend::Backend;
+use play_picross::board::Board;
+use play_picross::board::Tile;
+use play_picross::game::Game;
+use play_picross::gfx::load_atlas;
+use play_picross::generator::GeneratorOptions;
+use play_picross::gui::Gui;
+use play_picross::puzzle::Puzzle;
+use play_picross::replay;
+use play_picross::soft_backend::SoftwareBackend;
+
+#[test]
+fn translucent_fills_blend() {
+    let screen = SoftwareBackend::new(4, 4, None);
+    let mut backend: Box<dyn Backend> = Box::new(screen.clone());
+
+    backend.clear(Color::RGB(0xFF, 0xFF, 0xFF));
+    backend.fill_rect(Color::RGBA(0x00, 0x00, 0x00, 0x80), Rect::new(0, 0, 2, 4));
+    backend.draw_rect(Color::RGBA(0xFF, 0x00, 0x00, 0x80), Rect::new(2, 0, 2, 4));
+
+    assert_eq!(screen.pixel(0, 0), Some(Color::RGB(0x7F, 0x7F, 0x7F)));
+    assert_eq!(screen.pixel(2, 1), Some(Color::RGB(0xFF, 0x7F, 0x7F)));
+
+    // clear replaces whatever is there, alpha or not
+    backend.clear(Color::RGBA(0x10, 0x20, 0x30, 0x00));
+    assert_eq!(screen.pixel(0, 0), Some(Color::RGB(0x10, 0x20, 0x30)));
+}
+
+// The screen after playing the script against the staircase puzzle of
+// tests/replay.rs, with the sprites loaded.
+fn render(script: &str) -> SoftwareBackend {
+    let mut b = Board::new(4, 4);
+    for y in 0..4 {
+        for x in 0..(y + 1) {
+            b.set(x, y, Tile::Filled);
+        }
+    }
+
+    let screen = SoftwareBackend::new(640, 400, load_atlas());
+    for (ticks, e) in replay::read_events(script.as_bytes()).unwrap() {
+        screen.push_event_at(ticks, e);
+    }
+
+    let mut gui = Gui::new_with_backend(Box::new(screen.clone()));
+    let mut game = Game::new(Puzzle::from_solution(&b), GeneratorOptions::new(4, 4));
+    game.set_write_files(false);
+    gui.on_new_puzzle(game.get_puzzle());
+
+    while screen.has_events() {
+        game.step(&mut gui);
+    }
+    // long enough for a redraw
+    for _ in 0..5 {
+        game.step(&mut gui);
+    }
+
+    screen
+}
+
+// Darkest grey level in the rectangle.
+fn darkest(screen: &SoftwareBackend, x1: u32, y1: u32, x2: u32, y2: u32) -> u8 {
+    let mut level = 0xFF;
+    for y in y1..y2 {
+        for x in x1..x2 {
+            let (r, _, _) = screen.pixel(x, y).unwrap().rgb();
+            level = min(level, r);
+        }
+    }
+    level
+}
+
+#[test]
+fn error_shade_darkens_the_window() {
+    let screen = render("0 resize 640 400\n100 drop no/such/puzzle.txt\n");
+
+    // the light background, half shaded rather than blacked out
+    assert_eq!(screen.pixel(4, 4), Some(Color::RGB(0x67, 0x67, 0x67)));
+}
+
+// Clue markers on, and the bottom row filled to solve its clue.
+const SOLVE_BOTTOM_ROW: &'static str = "\
+0 resize 640 400
+50 key_down 0 B
+60 key_up 0 B
+100 mouse_motion 294 215 0
+110 mouse_down left 294 215
+150 mouse_motion 311 215 1
+200 mouse_motion 328 215 1
+250 mouse_motion 345 215 1
+300 mouse_up left 345 215
+400 mouse_motion 600 20 0
+";
+
+#[test]
+fn solved_clues_are_dimmed() {
+    let unsolved = render("0 resize 640 400\n50 key_down 0 B\n60 key_up 0 B\n");
+    let solved = render(SOLVE_BOTTOM_ROW);
+
+    // the 4 left of the bottom row is paler, but still there
+    let before = darkest(&unsolved, 265, 207, 285, 223);
+    let after = darkest(&solved, 265, 207, 285, 223);
+    assert!(before < after);
+    assert!(after < 0xD0);
+}
+
+#[test]
+fn block_range_shows_the_tiles() {
+    // hover the clue of the bottom row, with its first tile filled
+    let script = "\
+0 resize 640 400
+100 mouse_motion 294 215 0
+110 mouse_down left 294 215
+150 mouse_up left 294 215
+200 mouse_motion 277 215 0
+";
+    let screen = render(script);
+
+    // filled and empty tiles can still be told apart under the shade
+    let filled = screen.pixel(294, 215).unwrap();
+    let empty = screen.pixel(311, 215).unwrap();
+    assert!(filled != empty);
+    assert!(empty != Color::RGB(0x40, 0x80, 0xC0));
+}
+
+#[test]
+fn tools_fit_the_narrowest_window() {
+    // the narrowest window, where the label is hidden
+    let screen = render("0 resize 114 128\n");
+
+    // nothing is drawn over the toolbar margin inside its right edge
+    let margin = screen.pixel(109, 114);
+    for y in 114..127 {
+        for x in 109..113 {
+            assert!(screen.pixel(x, y) == margin, "({}, {}) is drawn over", x, y);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+// replay.rs
+//
+// Regression tests that play event scripts against the software backend
+// and look at the board left behind.
+
+#![cfg(feature = "gui")]
+
+extern crate play_picross;
+
+use play_picross::board::Board;
+use play_picross::board::Tile;
+use play_picross::paths;
+use play_picross::paths::XdgDir;
+use play_picross::puzzle::Clue;
+use play_picross::puzzle::Puzzle;
+use play_picross::replay;
+
+// A staircase, with rows 1, 2, 3 and 4 tiles long.
+//
+// In a 640 x 400 window the tiles are 17 pixels apart, and the top left
+// tile is centred on (294, 164).
+fn staircase() -> Puzzle {
+    let mut b = Board::new(4, 4);
+    for y in 0..4 {
+        for x in 0..(y + 1) {
+            b.set(x, y, Tile::Filled);
+        }
+    }
+
+    Puzzle::from_solution(&b)
+}
+
+fn play(script: &str) -> Board {
+    let events = replay::read_events(script.as_bytes()).unwrap();
+    replay::replay(staircase(), events, None, None).unwrap()
+}
+
+// The board as rows of '.', 'X' and '#'.
+fn rows(b: &Board) -> Vec<String> {
+    (0..b.height as u32).map(|y|
+        (0..b.width as u32).map(|x|
+            match b.get(x, y) {
+                Some(Tile::Empty) => '.',
+                Some(Tile::CrossedOut) => 'X',
+                Some(Tile::Filled) => '#',
+                None => '?'
+            }).collect()
+    ).collect()
+}
+
+const CLICK: &'static str = "\
+0 resize 640 400
+100 mouse_motion 294 164 0
+110 mouse_down left 294 164
+150 mouse_up left 294 164
+";
+
+// Fill the bottom row in one stroke.
+const DRAG: &'static str = "\
+0 resize 640 400
+100 mouse_motion 294 215 0
+110 mouse_down left 294 215
+150 mouse_motion 311 215 1
+200 mouse_motion 328 215 1
+250 mouse_motion 345 215 1
+300 mouse_up left 345 215
+";
+
+#[test]
+fn click_fills_a_tile() {
+    assert_eq!(rows(&play(CLICK)), vec!["#...", "....", "....", "...."]);
+}
+
+#[test]
+fn click_with_cross_tool() {
+    let script = "\
+0 resize 640 400
+50 key_down 0 2
+60 key_up 0 2
+100 mouse_motion 311 181 0
+110 mouse_down left 311 181
+150 mouse_up left 311 181
+";
+    assert_eq!(rows(&play(script)), vec!["....", ".X..", "....", "...."]);
+}
+
+#[test]
+fn drag_paints_a_line() {
+    assert_eq!(rows(&play(DRAG)), vec!["....", "....", "....", "####"]);
+}
+
+#[test]
+fn drag_is_one_undo_step() {
+    let undo = format!("{}400 key_down 0 Z\n410 key_up 0 Z\n", DRAG);
+    assert_eq!(rows(&play(&undo)), vec!["....", "....", "....", "...."]);
+
+    let redo = format!("{}500 key_down 0 X\n510 key_up 0 X\n", undo);
+    assert_eq!(rows(&play(&redo)), vec!["....", "....", "....", "####"]);
+}
+
+#[test]
+fn undo_steps_back_through_strokes() {
+    let script = format!("{}\
+400 mouse_motion 294 181 0
+410 mouse_down left 294 181
+450 mouse_up left 294 181
+500 key_down 0 Z
+510 key_up 0 Z
+", CLICK);
+    assert_eq!(rows(&play(&script)), vec!["#...", "....", "....", "...."]);
+
+    let script = format!("{}600 key_down 0 Z\n610 key_up 0 Z\n", script);
+    assert_eq!(rows(&play(&script)), vec!["....", "....", "....", "...."]);
+
+    // nothing left to undo
+    let script = format!("{}700 key_down 0 Z\n710 key_up 0 Z\n", script);
+    assert_eq!(rows(&play(&script)), vec!["....", "....", "....", "...."]);
+
+    let script = format!("{}800 key_down 0 X\n810 key_up 0 X\n", script);
+    assert_eq!(rows(&play(&script)), vec!["#...", "....", "....", "...."]);
+}
+
+#[test]
+fn new_stroke_after_undo_drops_redo() {
+    let script = format!("{}\
+400 key_down 0 Z
+410 key_up 0 Z
+500 mouse_motion 345 215 0
+510 mouse_down left 345 215
+550 mouse_up left 345 215
+600 key_down 0 X
+610 key_up 0 X
+", CLICK);
+    assert_eq!(rows(&play(&script)), vec!["....", "....", "....", "...#"]);
+}
+
+#[test]
+fn clue_marks_are_undone() {
+    // the clue of the first row is left of the board
+    let mark = "\
+0 resize 640 400
+100 mouse_motion 277 164 0
+110 mouse_down left 277 164
+150 mouse_up left 277 164
+";
+    assert!(play(mark).is_done(Clue::Row(0, 0)));
+
+    let undo = format!("{}200 key_down 0 Z\n210 key_up 0 Z\n", mark);
+    assert!(!play(&undo).is_done(Clue::Row(0, 0)));
+
+    let redo = format!("{}300 key_down 0 X\n310 key_up 0 X\n", undo);
+    assert!(play(&redo).is_done(Clue::Row(0, 0)));
+}
+
+#[test]
+fn keyboard_cursor_fills() {
+    let script = "\
+0 resize 640 400
+100 key_down 0 Right
+110 key_up 0 Right
+200 key_down 0 Down
+210 key_up 0 Down
+300 key_down 0 Space
+310 key_up 0 Space
+";
+    // the first key only shows the cursor, on the top left tile
+    assert_eq!(rows(&play(script)), vec!["....", "#...", "....", "...."]);
+}
+
+#[test]
+fn shift_paint_ends_on_focus_lost() {
+    // shift is let go while another window has focus
+    let script = "\
+0 resize 640 400
+100 key_down 1 Left Shift
+110 key_down 1 Right
+120 key_up 1 Right
+130 key_down 1 Right
+140 key_up 1 Right
+200 focus_lost
+300 key_down 0 Down
+310 key_up 0 Down
+";
+    assert_eq!(rows(&play(script)), vec!["###.", "....", "....", "...."]);
+
+    let undo = format!("{}400 key_down 0 Z\n410 key_up 0 Z\n", script);
+    assert_eq!(rows(&play(&undo)), vec!["....", "....", "....", "...."]);
+}
+
+#[test]
+fn long_press_after_last_event() {
+    // a finger held on the top left tile, still down when the script ends
+    let script = "\
+0 resize 640 400
+100 finger_down 1 0.459 0.41
+";
+    assert_eq!(rows(&play(script)), vec!["X...", "....", "....", "...."]);
+}
+
+// The board, too big for the window, with the minimap turned on.  The
+// minimap covers (552, 304) to (632, 364).
+fn play_with_minimap(board: &Board, script: &str) -> Board {
+    let mut p = Puzzle::new(board.width, board.height);
+    p.update(board.clone());
+
+    let script = format!("0 resize 640 400\n10 key_down 0 M\n20 key_up 0 M\n{}", script);
+    let events = replay::read_events(script.as_bytes()).unwrap();
+    replay::replay(p, events, None, None).unwrap()
+}
+
+#[test]
+fn minimap_takes_clicks_before_the_board() {
+    let blank = Board::new(40, 30);
+    let mut filled = blank.clone();
+    for y in 0..30 {
+        for x in 0..40 {
+            filled.set(x, y, Tile::Filled);
+        }
+    }
+
+    // left would fill the tile below, and right would clear it
+    for &(button, b) in [("left", &blank), ("right", &filled)].iter() {
+        let script = format!("\
+100 mouse_motion 600 340 0
+110 mouse_down {0} 600 340
+150 mouse_up {0} 600 340
+", button);
+        assert!(play_with_minimap(b, &script) == *b, "{} click painted", button);
+    }
+}
+
+#[test]
+fn pinned_clues_hide_the_board() {
+    // the first row has wide clues, and the rest a single 1, which
+    // leaves shade with no clues on it to their left
+    let mut solution = Board::new(40, 30);
+    for x in 0..20 {
+        solution.set(2 * x, 0, Tile::Filled);
+    }
+    for y in 1..30 {
+        solution.set(0, y, Tile::Filled);
+    }
+
+    // right clicks clear, so they need tiles to clear
+    let mut filled = solution.clone();
+    for y in 0..30 {
+        for x in 0..40 {
+            filled.set(x, y, Tile::Filled);
+        }
+    }
+
+    // pan the board up and left under the clues, then click the shade
+    // left of the row clues and above the column clues
+    for &(x, y) in [(3, 200), (280, 1)].iter() {
+        for button in ["left", "right"].iter() {
+            let script = format!("\
+0 resize 640 400
+100 mouse_motion 600 300 0
+110 mouse_down middle 600 300
+150 mouse_motion 300 150 2
+160 mouse_motion 10 10 2
+200 mouse_up middle 10 10
+300 mouse_motion {0} {1} 0
+310 mouse_down {2} {0} {1}
+350 mouse_up {2} {0} {1}
+", x, y, button);
+            let events = replay::read_events(script.as_bytes()).unwrap();
+            let mut p = Puzzle::from_solution(&solution);
+            if *button == "right" {
+                p.update(filled.clone());
+            }
+            let start = p.get_board().clone();
+
+            let b = replay::replay(p, events, None, None).unwrap();
+            assert!(b == start, "{} click at ({}, {}) painted", button, x, y);
+        }
+    }
+}
+
+#[test]
+fn replay_does_not_save() {
+    // only meaningful if nothing was there to start with
+    let saved = match paths::xdg_file(XdgDir::Data, "untitled.txt") {
+        Some(path) => path,
+        None => return
+    };
+    if saved.exists() {
+        return;
+    }
+
+    let script = format!("{}\
+400 key_down 0 E
+410 key_up 0 E
+420 key_down 0 E
+430 key_up 0 E
+500 mouse_motion 294 164 0
+510 mouse_down left 294 164
+550 mouse_up left 294 164
+600 key_down 0 S
+610 key_up 0 S
+", CLICK);
+    assert_eq!(rows(&play(&script)), vec!["#...", "....", "....", "...."]);
+    assert!(!saved.exists());
+}